@@ -0,0 +1,1825 @@
+//! 命令行参数、配置文件和请求项的解析
+
+use anyhow::{anyhow, Context, Result};
+use clap::{ArgEnum, ArgMatches, FromArgMatches, IntoApp, Parser};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::Level;
+use url::{ParseError, Url};
+
+use crate::print::{parse_theme, PrintSpec, DEFAULT_THEME};
+use crate::request::{split_items, SavedRequest};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct GET {
+    /// URLs to fetch; key=value arguments after a URL are appended to the query string
+    #[clap(required = true, parse(try_from_str = parse_get_arg))]
+    pub(crate) url: Vec<String>,
+    /// Stop at the first failed URL
+    #[clap(long)]
+    pub(crate) fail_fast: bool,
+    /// Fetch up to N URLs concurrently
+    #[clap(long, value_name = "N")]
+    pub(crate) parallel: Option<usize>,
+    /// With --parallel, print responses as they finish instead of in input order
+    #[clap(long, requires = "parallel")]
+    pub(crate) unordered: bool,
+}
+/// post/put/patch/request 共用的原始 body 参数
+#[derive(Parser, Debug)]
+pub(crate) struct BodyArgs {
+    /// Send the file contents verbatim as the request body
+    #[clap(long, parse(from_os_str))]
+    body_file: Option<PathBuf>,
+    /// Read the request body verbatim from standard input
+    #[clap(long, conflicts_with = "body-file")]
+    stdin: bool,
+    /// Send a raw JSON document (e.g. an array) as the request body
+    #[clap(long, parse(try_from_str = parse_raw_json), conflicts_with_all = &["body-file", "stdin"])]
+    raw: Option<String>,
+    /// Send the JSON document on the system clipboard as the request body
+    #[cfg(feature = "clipboard")]
+    #[clap(long, conflicts_with_all = &["body-file", "stdin", "raw"])]
+    clipboard: bool,
+}
+
+impl BodyArgs {
+    pub(crate) fn raw_body(&self) -> Option<RawBody> {
+        #[cfg(feature = "clipboard")]
+        if self.clipboard {
+            return Some(RawBody::Clipboard);
+        }
+        match (&self.body_file, &self.raw) {
+            (Some(path), _) => Some(RawBody::File(path.clone())),
+            _ if self.stdin => Some(RawBody::Stdin),
+            (None, Some(json)) => Some(RawBody::Json(json.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct POST {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+    #[clap(parse(try_from_str = parse_request_item))]
+    pub(crate) items: Vec<RequestItem>,
+    #[clap(flatten)]
+    pub(crate) body: BodyArgs,
+}
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct PUT {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+    #[clap(parse(try_from_str = parse_request_item))]
+    pub(crate) items: Vec<RequestItem>,
+    #[clap(flatten)]
+    pub(crate) body: BodyArgs,
+}
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct PATCH {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+    #[clap(parse(try_from_str = parse_request_item))]
+    pub(crate) items: Vec<RequestItem>,
+    #[clap(flatten)]
+    pub(crate) body: BodyArgs,
+}
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct DELETE {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+    #[clap(parse(try_from_str = parse_request_item))]
+    pub(crate) items: Vec<RequestItem>,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct HEAD {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct OPTIONS {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+}
+
+/// Send a request with an arbitrary method, e.g. PROPFIND or REPORT
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct REQUEST {
+    /// HTTP method to send
+    #[clap(short = 'X', long, parse(try_from_str = parse_method))]
+    pub(crate) method: Method,
+    #[clap(parse(try_from_str = parse_url))]
+    pub(crate) url: String,
+    #[clap(parse(try_from_str = parse_request_item))]
+    pub(crate) items: Vec<RequestItem>,
+    #[clap(flatten)]
+    pub(crate) body: BodyArgs,
+}
+
+/// Pretty-print and highlight a local JSON, XML or YAML file
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct FORMAT {
+    #[clap(name = "PATH", parse(from_os_str))]
+    pub(crate) path: PathBuf,
+}
+
+/// Fetch two URLs and print a line diff of their responses
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct DIFF {
+    #[clap(parse(try_from_str = parse_url))]
+    pub(crate) left: String,
+    #[clap(parse(try_from_str = parse_url))]
+    pub(crate) right: String,
+    /// Compare the status line and headers as well as the body
+    #[clap(long)]
+    pub(crate) include_headers: bool,
+}
+
+/// Send a GraphQL query as a POST request with a JSON envelope
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct GRAPHQL {
+    #[clap(parse(try_from_str = parse_url))]
+    url: String,
+    /// GraphQL query; read from --query-file or standard input when omitted
+    #[clap(conflicts_with = "query-file")]
+    query: Option<String>,
+    /// Read the query from this file
+    #[clap(long, parse(from_os_str))]
+    query_file: Option<PathBuf>,
+    /// Query variables as a JSON object
+    #[clap(long, value_name = "JSON", parse(try_from_str = parse_variables))]
+    variables: Option<Value>,
+    /// Operation to run when the query defines several
+    #[clap(long, value_name = "NAME")]
+    operation_name: Option<String>,
+}
+
+impl GRAPHQL {
+    // stdin 为 None 表示 --ignore-stdin，此时必须在参数或文件中给出查询
+    pub(crate) fn body(&self, stdin: Option<impl Read>) -> Result<RawBody> {
+        let query = match (&self.query, &self.query_file, stdin) {
+            (Some(query), _, _) => query.clone(),
+            (None, Some(path), _) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read query file {}", path.display()))?,
+            (None, None, Some(mut stdin)) => {
+                let mut query = String::new();
+                stdin.read_to_string(&mut query)?;
+                query
+            }
+            (None, None, None) => {
+                return Err(anyhow!(
+                    "No GraphQL query given, pass it as an argument or with --query-file"
+                ))
+            }
+        };
+        if query.trim().is_empty() {
+            return Err(anyhow!("GraphQL query is empty"));
+        }
+        let envelope = graphql_envelope(
+            &query,
+            self.variables.as_ref(),
+            self.operation_name.as_deref(),
+        );
+        Ok(RawBody::Json(envelope.to_string()))
+    }
+}
+
+/// List recent requests recorded with --history
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct HISTORY {
+    /// Number of most recent requests to show
+    #[clap(short = 'n', long, default_value = "20")]
+    pub(crate) limit: usize,
+}
+
+/// Replay a request saved with --save-request
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Parser, Debug)]
+pub(crate) struct REPLAY {
+    /// Saved request file
+    #[clap(name = "PATH", parse(try_from_str = parse_saved_request))]
+    pub(crate) saved: SavedRequest,
+    /// Override the saved method
+    #[clap(short = 'X', long, parse(try_from_str = parse_method))]
+    pub(crate) method: Option<Method>,
+    /// Override the saved URL
+    #[clap(long, parse(try_from_str = parse_url))]
+    url: Option<String>,
+    /// Extra request items; headers replace saved headers with the same name
+    #[clap(parse(try_from_str = parse_request_item))]
+    items: Vec<RequestItem>,
+}
+
+impl REPLAY {
+    pub(crate) fn request_items(&self) -> Result<Vec<RequestItem>> {
+        let overrides = split_items(&self.items).0;
+        let mut items = Vec::new();
+        for (name, value) in self.saved.headers.iter() {
+            let (name, value) = parse_header(&format!("{}:{}", name, value))?;
+            if !overrides.contains_key(&name) {
+                items.push(RequestItem::Header(name, value));
+            }
+        }
+        items.extend(self.items.iter().cloned());
+        Ok(items)
+    }
+}
+
+impl GET {
+    // 把 key=value 参数从 URL 列表中取出，作为查询参数附加到每个 URL 上
+    fn take_query(&mut self) -> Result<Vec<KvPair>> {
+        if self.url.first().is_some_and(|arg| is_query_arg(arg)) {
+            return Err(anyhow!("Expected a URL before {}", self.url[0]));
+        }
+        let (query, urls) = self.url.drain(..).partition(|arg| is_query_arg(arg));
+        self.url = urls;
+        query.iter().map(|arg: &String| arg.parse()).collect()
+    }
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum SubCommand {
+    Get(GET),
+    Post(POST),
+    Put(PUT),
+    Patch(PATCH),
+    Delete(DELETE),
+    Head(HEAD),
+    Options(OPTIONS),
+    Request(REQUEST),
+    Replay(REPLAY),
+    Format(FORMAT),
+    History(HISTORY),
+    Diff(DIFF),
+    Graphql(GRAPHQL),
+}
+
+impl SubCommand {
+    pub(crate) fn urls(&self) -> &[String] {
+        match self {
+            SubCommand::Get(args) => &args.url,
+            SubCommand::Post(args) => slice::from_ref(&args.url),
+            SubCommand::Put(args) => slice::from_ref(&args.url),
+            SubCommand::Patch(args) => slice::from_ref(&args.url),
+            SubCommand::Delete(args) => slice::from_ref(&args.url),
+            SubCommand::Head(args) => slice::from_ref(&args.url),
+            SubCommand::Options(args) => slice::from_ref(&args.url),
+            SubCommand::Request(args) => slice::from_ref(&args.url),
+            SubCommand::Graphql(args) => slice::from_ref(&args.url),
+            SubCommand::Replay(args) => {
+                slice::from_ref(args.url.as_ref().unwrap_or(&args.saved.url))
+            }
+            SubCommand::Format(_) | SubCommand::History(_) | SubCommand::Diff(_) => &[],
+        }
+    }
+}
+
+/// 命令行参数
+#[derive(Parser, Debug)]
+#[clap(version = "1.0", author = "Tonitr <tonitrnel@outlook.com>")]
+pub struct Opts {
+    #[clap(subcommand)]
+    pub(crate) subcmd: Option<SubCommand>,
+    #[clap(short, long, parse(from_occurrences))]
+    pub(crate) verbose: i32,
+    /// Extra request header, e.g. -H "Accept: application/json"
+    #[clap(
+        short = 'H',
+        long = "header",
+        multiple_occurrences(true),
+        number_of_values = 1,
+        parse(try_from_str = parse_header)
+    )]
+    pub(crate) headers: Vec<(HeaderName, HeaderValue)>,
+    /// Send If-None-Match with this ETag for a conditional request
+    #[clap(long, parse(try_from_str = parse_etag))]
+    pub(crate) etag: Option<HeaderValue>,
+    /// Send If-Modified-Since with this HTTP date for a conditional request
+    #[clap(long, value_name = "DATE", parse(try_from_str = parse_http_date))]
+    pub(crate) if_modified_since: Option<HeaderValue>,
+    /// Set the Accept header; json, xml, html, text, yaml and any expand to full MIME types
+    #[clap(long, value_name = "TYPE", parse(try_from_str = parse_accept))]
+    pub(crate) accept: Option<HeaderValue>,
+    /// Shorthand for --accept json
+    #[clap(long, conflicts_with = "accept")]
+    pub(crate) accept_json: bool,
+    /// Request only part of the body, e.g. 0-499, 500- or -500 (last 500 bytes)
+    #[clap(long, value_name = "START-END", parse(try_from_str = parse_range))]
+    pub(crate) range: Option<HeaderValue>,
+    /// Override the User-Agent header
+    #[clap(long, value_name = "STRING")]
+    pub(crate) user_agent: Option<String>,
+    /// Do not send the User-Agent and Accept-Encoding headers added by default
+    #[clap(long)]
+    pub(crate) no_default_headers: bool,
+    /// Send an `Authorization: Bearer <TOKEN>` header
+    #[clap(long)]
+    pub(crate) auth_bearer: Option<String>,
+    /// HTTP Basic authentication credentials as user:pass, prompts for the password if only user is given
+    #[clap(short, long, parse(try_from_str = parse_basic_auth))]
+    pub(crate) auth: Option<BasicAuth>,
+    /// Query string parameter as key=value, appended to the URL
+    #[clap(
+        short,
+        long,
+        multiple_occurrences(true),
+        number_of_values = 1,
+        parse(try_from_str = parse_kv_pair)
+    )]
+    pub(crate) query: Vec<KvPair>,
+    /// Serialize body fields as application/x-www-form-urlencoded instead of JSON (multipart when a key@path field is given)
+    #[clap(short, long)]
+    pub(crate) form: bool,
+    /// Send body fields as multipart/form-data, uploading key@path fields as files
+    #[clap(long, conflicts_with = "form")]
+    pub(crate) multipart: bool,
+    /// Start the JSON body from this file; body fields override its keys
+    #[clap(long, value_name = "FILE", parse(from_os_str), conflicts_with_all = &["form", "multipart"])]
+    pub(crate) body_template: Option<PathBuf>,
+    /// Form field whose value is sent literally, even if it contains & or =; implies --form
+    #[clap(
+        long,
+        value_name = "KEY=VALUE",
+        multiple_occurrences(true),
+        number_of_values = 1,
+        parse(try_from_str = parse_kv_pair),
+        conflicts_with_all = &["multipart", "body-template"]
+    )]
+    pub(crate) data_urlencode: Vec<KvPair>,
+    /// Raw body chunk like curl -d; repeated chunks are joined with & under --form, otherwise concatenated
+    #[clap(
+        long,
+        value_name = "CHUNK",
+        multiple_occurrences(true),
+        number_of_values = 1,
+        conflicts_with_all = &["multipart", "body-template"]
+    )]
+    pub(crate) data: Vec<String>,
+    /// Gzip the request body and send Content-Encoding: gzip
+    #[clap(long)]
+    pub(crate) compress: bool,
+    /// Send PUT/PATCH/DELETE/... as POST with the intended method in X-HTTP-Method-Override
+    #[clap(long)]
+    pub(crate) method_override: bool,
+    /// Give up when the server does not respond within this many seconds
+    #[clap(long, value_name = "SECONDS", parse(try_from_str = parse_timeout))]
+    pub(crate) timeout: Option<f64>,
+    /// Give up when a request, including retries and redirects, takes longer than this many seconds
+    #[clap(long, value_name = "SECONDS", parse(try_from_str = parse_max_time))]
+    pub(crate) max_time: Option<Duration>,
+    /// Follow at most N redirects (cannot be combined with --no-follow)
+    #[clap(long, conflicts_with = "no-follow")]
+    pub(crate) max_redirects: Option<usize>,
+    /// Do not follow redirects, print the 3xx response as-is
+    #[clap(long)]
+    pub(crate) no_follow: bool,
+    /// Save the raw response body to a file instead of printing it
+    #[clap(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+    /// Download the body like `curl -O`, naming the file after the response
+    #[clap(short, long)]
+    pub(crate) download: bool,
+    /// Hide the download progress bar
+    #[clap(long)]
+    pub(crate) quiet: bool,
+    /// Disable colored output, same as --color never
+    #[clap(long)]
+    pub(crate) no_color: bool,
+    /// When to use colors and syntax highlighting
+    #[clap(long, arg_enum, default_value = "auto")]
+    pub(crate) color: ColorChoice,
+    /// Reindent JSON response bodies (default)
+    #[clap(long, overrides_with = "no-pretty")]
+    pretty: bool,
+    /// Print JSON response bodies exactly as received
+    #[clap(long, overrides_with = "pretty")]
+    pub(crate) no_pretty: bool,
+    /// Show buffered response bodies through $PAGER (default less -R), on by default when stdout is a terminal
+    #[clap(long, overrides_with = "no-pager")]
+    pub(crate) pager: bool,
+    /// Print output directly instead of through a pager
+    #[clap(long, overrides_with = "pager")]
+    pub(crate) no_pager: bool,
+    /// Syntax highlighting theme, see --list-themes
+    #[clap(long, default_value = DEFAULT_THEME, parse(try_from_str = parse_theme))]
+    pub(crate) theme: String,
+    /// Print the available syntax highlighting themes and exit
+    #[clap(long)]
+    pub(crate) list_themes: bool,
+    /// Load and persist cookies in a named session (or a path to a session file)
+    #[clap(long)]
+    pub(crate) session: Option<String>,
+    /// Send a cookie as name=value; merged with --session cookies, -H "Cookie: ..." wins over both
+    #[clap(
+        long = "cookie",
+        multiple_occurrences(true),
+        number_of_values = 1,
+        parse(try_from_str = parse_kv_pair)
+    )]
+    pub(crate) cookies: Vec<KvPair>,
+    /// Skip TLS certificate verification (for self-signed certificates only)
+    #[clap(short = 'k', long)]
+    pub(crate) insecure: bool,
+    /// Print the server certificate of the TLS connection (version and cipher show n/a)
+    #[clap(long, conflicts_with = "json")]
+    pub(crate) verbose_tls: bool,
+    /// Only use HTTP/1.1
+    #[clap(long)]
+    pub(crate) http1: bool,
+    /// Speak HTTP/2 without negotiation, e.g. for h2c servers
+    #[clap(long, conflicts_with = "http1")]
+    pub(crate) http2_prior_knowledge: bool,
+    /// PEM client certificate for mutual TLS, may also contain the private key
+    #[clap(long, parse(from_os_str))]
+    pub(crate) cert: Option<PathBuf>,
+    /// PEM private key for --cert, if not bundled in the certificate file
+    #[clap(long, parse(from_os_str), requires = "cert")]
+    pub(crate) key: Option<PathBuf>,
+    /// Proxy for all requests, e.g. http://proxy:3128 or socks5://127.0.0.1:1080
+    #[clap(long, conflicts_with = "no-proxy")]
+    pub(crate) proxy: Option<String>,
+    /// Ignore proxy settings and connect directly
+    #[clap(long)]
+    pub(crate) no_proxy: bool,
+    /// Open a new connection for every request (slower, each one pays for TCP and TLS setup again)
+    #[clap(long)]
+    pub(crate) no_keepalive: bool,
+    /// Send requests from this local IP address
+    #[clap(long, value_name = "ADDR", parse(try_from_str = parse_interface))]
+    pub(crate) interface: Option<IpAddr>,
+    /// Connect to ADDR instead of resolving HOST, e.g. example.com:443:127.0.0.1
+    #[clap(
+        long,
+        value_name = "HOST:PORT:ADDR",
+        multiple_occurrences(true),
+        number_of_values = 1,
+        parse(try_from_str = parse_resolve)
+    )]
+    pub(crate) resolve: Vec<(String, SocketAddr)>,
+    /// Send this Host header instead of the URL's host; TLS SNI and certificate checks still use the URL host
+    #[clap(long, value_name = "HOST", parse(try_from_str = parse_host_header))]
+    pub(crate) host_header: Option<HeaderValue>,
+    /// Print timing and body size details after the response
+    #[clap(long)]
+    pub(crate) meta: bool,
+    /// Break [timing] down into DNS lookup, time to first byte and total (connect and TLS show n/a)
+    #[clap(long)]
+    pub(crate) timings: bool,
+    /// Send the request N times and print latency statistics
+    #[clap(long, value_name = "N")]
+    pub(crate) repeat: Option<u32>,
+    /// Log network activity to stderr: error, warn, info, debug or trace (RUST_LOG also works)
+    #[clap(long, value_name = "LEVEL")]
+    pub(crate) log_level: Option<Level>,
+    /// Show the underlying error chain after the short message for connection failures
+    #[clap(long)]
+    pub(crate) debug: bool,
+    /// Print only the status line and headers
+    #[clap(long = "headers", conflicts_with = "body-only")]
+    pub(crate) headers_only: bool,
+    /// Print only the response body
+    #[clap(long = "body")]
+    pub(crate) body_only: bool,
+    /// Print nothing for 2xx/3xx responses, only the body of 4xx/5xx ones (implies --check-status)
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "headers-only", "body-only", "print", "json", "porcelain", "fail", "output", "download",
+        ]
+    )]
+    pub(crate) body_only_on_error: bool,
+    /// What to print: H request headers, B request body, h response headers, b response body
+    #[clap(
+        short,
+        long,
+        value_name = "HBhb",
+        parse(try_from_str = parse_print_spec),
+        conflicts_with_all = &["headers-only", "body-only"]
+    )]
+    pub(crate) print: Option<PrintSpec>,
+    /// Exit with code 4 on 4xx and 5 on 5xx responses
+    #[clap(long)]
+    pub(crate) check_status: bool,
+    /// On 4xx/5xx responses print nothing but a short error and exit nonzero
+    #[clap(long)]
+    pub(crate) fail: bool,
+    /// Retry connection errors and 5xx responses up to N times
+    #[clap(long, default_value = "0")]
+    pub(crate) retries: u32,
+    /// Initial delay between retries in milliseconds, doubled after each attempt
+    #[clap(long, default_value = "500")]
+    pub(crate) retry_delay: u64,
+    /// Also retry non-idempotent methods such as POST and PATCH
+    #[clap(long)]
+    pub(crate) retry_all: bool,
+    /// Print the response as a single JSON object instead of formatted output
+    #[clap(
+        long,
+        conflicts_with_all = &["output", "download", "headers-only", "body-only", "print"]
+    )]
+    pub(crate) json: bool,
+    /// Print one line "STATUS<TAB>CONTENT-TYPE<TAB>BYTES" (CONTENT-TYPE is - if absent), then the body bytes
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "json", "output", "download", "headers-only", "body-only", "print",
+            "select", "raw-output", "stream", "filter",
+        ]
+    )]
+    pub(crate) porcelain: bool,
+    /// Use https:// for URLs given without a scheme
+    #[clap(long)]
+    pub(crate) https: bool,
+    /// Abort when a printed response body exceeds this many bytes (0 = unlimited)
+    #[clap(long, default_value = "104857600")]
+    pub(crate) max_body_size: u64,
+    /// Leave ${VAR} references to unset environment variables as-is instead of failing
+    #[clap(long)]
+    pub(crate) allow_unset: bool,
+    /// Append each request to the history log in the config directory, see `history`
+    #[clap(long)]
+    pub(crate) history: bool,
+    /// Never read from stdin, not even to prompt for a password
+    #[clap(long)]
+    pub(crate) ignore_stdin: bool,
+    /// Sort response headers by name and align their values
+    #[clap(long)]
+    pub(crate) sort_headers: bool,
+    /// Show each Set-Cookie attribute on its own line
+    #[clap(long)]
+    pub(crate) expand_cookies: bool,
+    /// Print only the JSON value at a dotted path, e.g. data.items.0.name
+    #[clap(long, value_name = "PATH")]
+    pub(crate) select: Option<String>,
+    /// Write the body bytes as received, without labels, highlighting or formatting
+    #[clap(long, conflicts_with = "select")]
+    pub(crate) raw_output: bool,
+    /// Print the body without syntax highlighting; status and headers stay colored
+    #[clap(long)]
+    pub(crate) no_body_highlight: bool,
+    /// Print text bodies as they arrive, without buffering or highlighting
+    #[clap(long, conflicts_with_all = &["select", "raw-output"])]
+    pub(crate) stream: bool,
+    /// Pipe the response body through a shell command and print its output, e.g. "jq .data"
+    #[clap(long, value_name = "COMMAND", conflicts_with_all = &["select", "raw-output", "stream"])]
+    pub(crate) filter: Option<String>,
+    /// Percent-decode strings in JSON and form bodies for display
+    #[clap(long, conflicts_with_all = &["raw-output", "stream"])]
+    pub(crate) decode: bool,
+    /// Save the composed request to a file that `replay` can send again
+    #[clap(long, value_name = "PATH", parse(from_os_str))]
+    pub(crate) save_request: Option<PathBuf>,
+    /// Print the request that would be sent without sending it
+    #[clap(long, alias = "dry-run")]
+    pub(crate) offline: bool,
+    /// Print the equivalent curl command for the request
+    #[clap(long)]
+    pub(crate) curl: bool,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ColorChoice {
+    Always,
+    Auto,
+    Never,
+}
+
+/// `key=value` 形式的键值对，值中的 `@` 按字面处理
+#[derive(Debug, PartialEq)]
+pub struct KvPair {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for KvPair {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!(format!("Failed to parse {}", s)))?;
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// body 字段，`key:=json` 的值按原始 JSON 解析，`key@path` 只记录文件路径，发送时才读取
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyField {
+    pub key: String,
+    /// `file` 有值时为 `Value::Null`
+    pub value: Value,
+    pub file: Option<PathBuf>,
+}
+
+impl FromStr for BodyField {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // key:=value 的值按原始 JSON 解析，其余情况退化为字符串
+        match (s.find(":="), s.find(['=', '@'])) {
+            (Some(index), Some(other)) if index < other => {
+                let (key, raw) = (&s[..index], &s[index + 2..]);
+                let value = serde_json::from_str(raw)
+                    .with_context(|| format!("Invalid JSON value for field {}: {}", key, raw))?;
+                Ok(Self {
+                    key: key.to_string(),
+                    value,
+                    file: None,
+                })
+            }
+            (_, Some(index)) if s[index..].starts_with('@') => {
+                let (key, path) = (&s[..index], &s[index + 1..]);
+                Ok(Self {
+                    key: key.to_string(),
+                    value: Value::Null,
+                    file: Some(PathBuf::from(path)),
+                })
+            }
+            _ => {
+                let KvPair { key, value } = s.parse()?;
+                Ok(Self {
+                    key,
+                    value: Value::String(value),
+                    file: None,
+                })
+            }
+        }
+    }
+}
+
+/// 与 HTTPie 一致：`Name:Value` 为请求头，`key=value` / `key:=json` / `key@file` 为 body 字段
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestItem {
+    Header(HeaderName, HeaderValue),
+    Field(BodyField),
+}
+
+impl FromStr for RequestItem {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = s
+            .find([':', '=', '@'])
+            .ok_or_else(|| anyhow!(format!("Failed to parse {}", s)))?;
+        if s[index..].starts_with(':') && !s[index..].starts_with(":=") {
+            let (name, value) = parse_header(s)?;
+            Ok(Self::Header(name, value))
+        } else {
+            Ok(Self::Field(s.parse()?))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BasicAuth {
+    pub(crate) username: String,
+    // 只给出用户名时为 None，解析完参数后再提示输入
+    pub(crate) password: Option<String>,
+}
+
+impl BasicAuth {
+    // 密码不写在命令行上，避免留在 shell 历史里；非交互环境下无法提示，直接报错
+    fn prompt_password(&mut self, interactive: bool) -> Result<()> {
+        if self.password.is_some() {
+            return Ok(());
+        }
+        if !interactive {
+            return Err(anyhow!(
+                "No password given for --auth {}, use --auth {}:PASSWORD when stdin is not a terminal",
+                self.username,
+                self.username
+            ));
+        }
+        let password = rpassword::prompt_password(format!("Password for {}: ", self.username))
+            .context("Failed to read password")?;
+        self.password = Some(password);
+        Ok(())
+    }
+}
+
+impl FromStr for BasicAuth {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // 只按第一个冒号切分，密码本身可以包含冒号
+        let (username, password) = match s.split_once(':') {
+            Some((username, password)) => (username, Some(password)),
+            None => (s, None),
+        };
+        if username.is_empty() {
+            return Err(anyhow!(format!(
+                "Failed to parse auth {}, missing username",
+                s
+            )));
+        }
+        Ok(Self {
+            username: username.to_string(),
+            password: password.map(str::to_string),
+        })
+    }
+}
+
+/// 校验 URL 是否合法，返回原始字符串，补全规则见 [`normalize_url`]
+pub fn parse_url(url: &str) -> Result<String, ParseError> {
+    // 引用了未设置的环境变量时留到发送前再报错，那时才知道是否指定了 --allow-unset
+    if let Ok(expanded) = expand_env(url, false) {
+        normalize_url(&expanded, false)?;
+    }
+    Ok(url.into())
+}
+
+/// 展开 `${VAR}` 环境变量引用，`$$` 表示字面量 `$`
+pub fn expand_env(s: &str, allow_unset: bool) -> Result<String> {
+    expand_vars(s, |name| env::var(name).ok(), allow_unset)
+}
+
+fn expand_vars(
+    s: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    allow_unset: bool,
+) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(index) = rest.find('$') {
+        out.push_str(&rest[..index]);
+        rest = &rest[index..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        match rest.strip_prefix("${").and_then(|r| r.split_once('}')) {
+            Some((name, after)) => {
+                match lookup(name) {
+                    Some(value) => out.push_str(&value),
+                    None if allow_unset => out.push_str(&rest[..name.len() + 3]),
+                    None => {
+                        return Err(anyhow!(
+                            "Environment variable {} is not set (referenced in {})",
+                            name,
+                            s
+                        ))
+                    }
+                }
+                rest = after;
+            }
+            // 不是 ${VAR} 形式的 $ 原样保留
+            None => {
+                out.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+// 只展开字符串类型的字段值，key@path 读取的文件内容和请求头保持原样
+pub(crate) fn expand_items(items: &[RequestItem], allow_unset: bool) -> Result<Vec<RequestItem>> {
+    items
+        .iter()
+        .map(|item| match item {
+            RequestItem::Field(BodyField {
+                key,
+                value: Value::String(value),
+                file: None,
+            }) => Ok(RequestItem::Field(BodyField {
+                key: key.clone(),
+                value: Value::String(expand_env(value, allow_unset)?),
+                file: None,
+            })),
+            item => Ok(item.clone()),
+        })
+        .collect()
+}
+
+/// 与 HTTPie 一致补全 URL：没有 scheme 时加上 `http://`（`https` 为真时加 `https://`），
+/// `:8080/path` 和 `/path` 指向 localhost，不带方括号的 IPv6 地址如 `::1/path` 会补上方括号
+pub fn normalize_url(url: &str, https: bool) -> Result<String, ParseError> {
+    if url.contains("://") {
+        Url::parse(url)?;
+        return Ok(url.into());
+    }
+    let (addr, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+    let host = match url.strip_prefix(':') {
+        _ if addr.parse::<Ipv6Addr>().is_ok() => format!("[{}]{}", addr, path),
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("localhost{}", rest),
+        Some(rest) => format!("localhost:{}", rest),
+        None if url.starts_with('/') => format!("localhost{}", url),
+        None => url.into(),
+    };
+    let url = format!("{}://{}", if https { "https" } else { "http" }, host);
+    Url::parse(&url)?;
+    Ok(url)
+}
+
+/// 解析 `key=value`
+pub fn parse_kv_pair(s: &str) -> Result<KvPair, anyhow::Error> {
+    s.parse()
+}
+
+// = 之前没有 URL 里才会出现的字符时，按 key=value 查询参数处理
+pub(crate) fn is_query_arg(s: &str) -> bool {
+    match s.split_once('=') {
+        Some((key, _)) => !key.is_empty() && !key.contains(['/', ':', '?', '#', '[']),
+        None => false,
+    }
+}
+
+fn parse_get_arg(s: &str) -> Result<String, anyhow::Error> {
+    if is_query_arg(s) {
+        s.parse::<KvPair>()?;
+        return Ok(s.into());
+    }
+    Ok(parse_url(s)?)
+}
+
+pub(crate) fn parse_request_item(s: &str) -> Result<RequestItem, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), anyhow::Error> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Failed to parse header {}, expected Name:Value", s))?;
+    let name = HeaderName::from_str(name.trim())
+        .map_err(|e| anyhow!("Invalid header name {:?}: {}", name, e))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|e| anyhow!("Invalid header value {:?}: {}", value, e))?;
+    Ok((name, value))
+}
+
+// --host-header 的值原样作为 Host 发送，不能为空或包含空白
+fn parse_host_header(s: &str) -> Result<HeaderValue, anyhow::Error> {
+    if s.is_empty() || s.contains(char::is_whitespace) {
+        return Err(anyhow!("Invalid host {:?}", s));
+    }
+    HeaderValue::from_str(s).map_err(|e| anyhow!("Invalid host {}: {}", s, e))
+}
+
+// 未加引号的 ETag 自动补上双引号，W/"..." 和 * 保持原样
+fn parse_etag(s: &str) -> Result<HeaderValue, anyhow::Error> {
+    let value = if s == "*" || s.starts_with('"') || s.starts_with("W/") {
+        s.to_string()
+    } else {
+        format!("\"{}\"", s)
+    };
+    HeaderValue::from_str(&value).map_err(|e| anyhow!("Invalid ETag {}: {}", s, e))
+}
+
+// 含有 / 的值视为完整的 MIME 类型，原样使用
+fn parse_accept(s: &str) -> Result<HeaderValue, anyhow::Error> {
+    let value = match s {
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "html" => "text/html",
+        "text" => "text/plain",
+        "yaml" => "application/yaml",
+        "any" => "*/*",
+        s if s.contains('/') => s,
+        _ => {
+            return Err(anyhow!(
+                "Unknown type {}, use json, xml, html, text, yaml, any or a full MIME type",
+                s
+            ))
+        }
+    };
+    HeaderValue::from_str(value).map_err(|e| anyhow!("Invalid Accept value {}: {}", s, e))
+}
+
+pub(crate) fn parse_http_date(s: &str) -> Result<HeaderValue, anyhow::Error> {
+    httpdate::parse_http_date(s).map_err(|_| {
+        anyhow!(
+            "Invalid HTTP date {}, expected e.g. \"Wed, 21 Oct 2015 07:28:00 GMT\"",
+            s
+        )
+    })?;
+    Ok(HeaderValue::from_str(s)?)
+}
+
+// 只支持单个字节区间，start-end 的两端都包含在内
+fn parse_range(s: &str) -> Result<HeaderValue, anyhow::Error> {
+    let invalid = || anyhow!("Invalid range {}, expected START-END, START- or -SUFFIX", s);
+    let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+    let number = |v: &str| match v {
+        "" => Ok(None),
+        v if v.bytes().all(|b| b.is_ascii_digit()) => {
+            v.parse::<u64>().map(Some).map_err(|_| invalid())
+        }
+        _ => Err(invalid()),
+    };
+    match (number(start)?, number(end)?) {
+        (None, None) => return Err(invalid()),
+        (Some(start), Some(end)) if start > end => return Err(invalid()),
+        _ => {}
+    }
+    Ok(HeaderValue::from_str(&format!("bytes={}", s))?)
+}
+
+pub(crate) fn parse_max_time(s: &str) -> Result<Duration, anyhow::Error> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(positive_seconds)
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid --max-time {}, expected a positive number of seconds",
+                s
+            )
+        })
+}
+
+fn parse_interface(s: &str) -> Result<IpAddr, anyhow::Error> {
+    s.parse()
+        .map_err(|_| anyhow!("Invalid --interface {}, expected a local IP address", s))
+}
+
+// 与 curl 相同，IPv6 地址可以带或不带方括号
+fn parse_resolve(s: &str) -> Result<(String, SocketAddr), anyhow::Error> {
+    let invalid = || anyhow!("Invalid --resolve {}, expected HOST:PORT:ADDR", s);
+    let mut parts = s.splitn(3, ':');
+    let (host, port, addr) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(host), Some(port), Some(addr)) if !host.is_empty() => (host, port, addr),
+        _ => return Err(invalid()),
+    };
+    let port: u16 = port.parse().map_err(|_| invalid())?;
+    let addr: IpAddr = addr
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|_| invalid())?;
+    Ok((host.to_ascii_lowercase(), SocketAddr::new(addr, port)))
+}
+
+fn parse_basic_auth(s: &str) -> Result<BasicAuth, anyhow::Error> {
+    s.parse()
+}
+
+// 秒数必须为正，且能表示为 Duration（NaN、无穷大和过大的值都不行）
+pub(crate) fn positive_seconds(secs: f64) -> Option<Duration> {
+    if secs > 0.0 {
+        Duration::try_from_secs_f64(secs).ok()
+    } else {
+        None
+    }
+}
+
+fn parse_timeout(s: &str) -> Result<f64, anyhow::Error> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|secs| positive_seconds(*secs).is_some())
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid --timeout {}, expected a positive number of seconds",
+                s
+            )
+        })
+}
+
+fn parse_saved_request(s: &str) -> Result<SavedRequest, anyhow::Error> {
+    SavedRequest::load(Path::new(s))
+}
+
+fn parse_print_spec(s: &str) -> Result<PrintSpec, anyhow::Error> {
+    s.parse()
+}
+
+pub(crate) fn parse_method(s: &str) -> Result<Method, anyhow::Error> {
+    Method::from_bytes(s.as_bytes()).map_err(|_| anyhow!("Invalid HTTP method {}", s))
+}
+
+fn parse_variables(s: &str) -> Result<Value, anyhow::Error> {
+    match serde_json::from_str(s) {
+        Ok(value @ Value::Object(_)) => Ok(value),
+        Ok(_) => Err(anyhow!("--variables must be a JSON object: {}", s)),
+        Err(e) => Err(anyhow!("Invalid JSON for --variables: {}", e)),
+    }
+}
+
+// 标准的 GraphQL over HTTP 请求体，没有变量和操作名时省略对应字段
+fn graphql_envelope(query: &str, variables: Option<&Value>, operation: Option<&str>) -> Value {
+    let mut envelope = Map::new();
+    envelope.insert("query".into(), query.into());
+    if let Some(variables) = variables {
+        envelope.insert("variables".into(), variables.clone());
+    }
+    if let Some(operation) = operation {
+        envelope.insert("operationName".into(), operation.into());
+    }
+    Value::Object(envelope)
+}
+
+pub(crate) fn parse_raw_json(s: &str) -> Result<String, anyhow::Error> {
+    serde_json::from_str::<Value>(s).with_context(|| format!("Invalid JSON for --raw: {}", s))?;
+    Ok(s.into())
+}
+
+// 原始 body 的来源，与 key=value 字段互斥
+#[derive(Debug)]
+pub(crate) enum RawBody {
+    File(PathBuf),
+    Stdin,
+    Json(String),
+    // replay 时保存下来的原始 body
+    Text(String),
+    // --data 拼接后的 body，以及根据内容推断的 Content-Type
+    Data(String, &'static str),
+    #[cfg(feature = "clipboard")]
+    Clipboard,
+}
+
+// 剪贴板里的内容按 JSON 发送，读不到或不是 JSON 时直接报错
+#[cfg(feature = "clipboard")]
+pub(crate) fn read_clipboard() -> Result<String> {
+    let text = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|e| match e {
+            arboard::Error::ContentNotAvailable => anyhow!("Clipboard does not contain any text"),
+            e => anyhow!("Failed to read the clipboard: {}", e),
+        })?;
+    if text.trim().is_empty() {
+        return Err(anyhow!("Clipboard is empty"));
+    }
+    serde_json::from_str::<Value>(&text).context("Clipboard does not contain valid JSON")?;
+    Ok(text)
+}
+
+// 与 curl -d 相同，表单模式下用 & 连接；否则直接拼接，能解析为 JSON 时按 JSON 发送
+pub(crate) fn data_body(chunks: &[String], form: bool) -> RawBody {
+    if form {
+        return RawBody::Data(chunks.join("&"), "application/x-www-form-urlencoded");
+    }
+    let body = chunks.concat();
+    let content_type = match serde_json::from_str::<Value>(&body) {
+        Ok(_) => mime::APPLICATION_JSON.as_ref(),
+        Err(_) => mime::TEXT_PLAIN_UTF_8.as_ref(),
+    };
+    RawBody::Data(body, content_type)
+}
+
+pub(crate) fn read_body_file(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Failed to read body file {}", path.display()))
+}
+
+pub(crate) fn read_body<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    reader
+        .read_to_end(&mut body)
+        .context("Failed to read body from stdin")?;
+    Ok(body)
+}
+
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .map(|dir| dir.join("httpie-rust"))
+}
+
+/// 配置文件 `<config_dir>/httpie-rust/config.toml` 中的默认设置，命令行参数优先。
+///
+/// ```toml
+/// # 默认请求头，命令行 -H 指定同名请求头时以命令行为准
+/// headers = ["Accept: application/json"]
+/// # 默认高亮主题，见 --list-themes
+/// theme = "Solarized (dark)"
+/// # 默认超时秒数
+/// timeout = 30
+/// # 为 false 时相当于 --no-follow
+/// follow_redirects = false
+/// ```
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub headers: Vec<String>,
+    pub theme: Option<String>,
+    pub timeout: Option<f64>,
+    pub follow_redirects: Option<bool>,
+}
+
+impl Config {
+    /// 文件不存在时返回默认配置，格式错误时报错
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("Invalid config file {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read config file {}", path.display()))
+            }
+        }
+    }
+
+    // 只填充命令行没有显式指定的参数
+    fn apply(&self, opts: &mut Opts, matches: &ArgMatches) -> Result<()> {
+        let headers = self
+            .headers
+            .iter()
+            .map(|h| parse_header(h).context("Invalid header in config file"))
+            .collect::<Result<Vec<_>>>()?;
+        opts.headers.splice(0..0, headers);
+        if let Some(ref theme) = self.theme {
+            if matches.occurrences_of("theme") == 0 {
+                opts.theme = parse_theme(theme).context("Invalid theme in config file")?;
+            }
+        }
+        if let (None, Some(secs)) = (opts.timeout, self.timeout) {
+            positive_seconds(secs).ok_or_else(|| {
+                anyhow!(
+                    "Invalid timeout {} in config file, expected a positive number of seconds",
+                    secs
+                )
+            })?;
+            opts.timeout = Some(secs);
+        }
+        if self.follow_redirects == Some(false) && opts.max_redirects.is_none() {
+            opts.no_follow = true;
+        }
+        Ok(())
+    }
+}
+
+/// 解析命令行参数并合并配置文件中的默认设置
+pub fn parse_opts() -> Result<Opts> {
+    let matches = Opts::into_app().get_matches();
+    parse_opts_from(&matches, config_dir().map(|dir| dir.join("config.toml")))
+}
+
+pub(crate) fn parse_opts_from(matches: &ArgMatches, config: Option<PathBuf>) -> Result<Opts> {
+    let mut opts =
+        Opts::from_arg_matches(matches).ok_or_else(|| anyhow!("Failed to parse arguments"))?;
+    if let Some(SubCommand::Get(ref mut args)) = opts.subcmd {
+        let query = args.take_query()?;
+        opts.query.extend(query);
+    }
+    if let Some(path) = config {
+        Config::load(&path)?.apply(&mut opts, matches)?;
+    }
+    if let Some(ref mut auth) = opts.auth {
+        auth.prompt_password(!opts.ignore_stdin && io::stdin().is_terminal())?;
+    }
+    Ok(opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::temp_path;
+    use crate::print::{curl_command, DEFAULT_THEME};
+    use crate::request::{build_client, build_headers};
+    use reqwest::{header, Client};
+
+    #[test]
+    fn parse_url_works() {
+        assert!(parse_url("abc").is_ok());
+        assert!(parse_url("").is_err());
+        assert!(parse_url("http://").is_err());
+        assert!(parse_url("exa mple.com").is_err());
+        assert!(parse_url("http://abc.xyz").is_ok());
+        assert!(parse_url("https://httpbin.org/post").is_ok());
+        assert!(parse_url("http://[::1]:8080/path").is_ok());
+        assert!(parse_url("[::1]:8080/path").is_ok());
+        assert!(parse_url("https://[2001:db8::1]/").is_ok());
+        assert!(parse_url("::1").is_ok());
+        assert!(parse_url("http://[::1/").is_err());
+        assert!(parse_url("http://[::g]/").is_err());
+    }
+    #[test]
+    fn parse_kv_pair_works() {
+        assert!(parse_kv_pair("a").is_err());
+        assert_eq!(
+            parse_kv_pair("a=1").unwrap(),
+            KvPair {
+                key: "a".into(),
+                value: "1".into()
+            }
+        );
+        assert_eq!(
+            parse_kv_pair("b=").unwrap(),
+            KvPair {
+                key: "b".into(),
+                value: "".into()
+            }
+        );
+    }
+    #[test]
+    fn delete_subcommand_works() {
+        let opts =
+            Opts::try_parse_from(["httpie", "delete", "https://httpbin.org/delete"]).unwrap();
+        assert!(matches!(opts.subcmd, Some(SubCommand::Delete(ref args)) if args.items.is_empty()));
+        let opts = Opts::try_parse_from(["httpie", "delete", "https://httpbin.org/delete", "id=1"])
+            .unwrap();
+        assert!(matches!(opts.subcmd, Some(SubCommand::Delete(ref args)) if args.items.len() == 1));
+    }
+    #[test]
+    fn head_subcommand_works() {
+        let opts = Opts::try_parse_from(["httpie", "head", "https://example.com"]).unwrap();
+        assert!(
+            matches!(opts.subcmd, Some(SubCommand::Head(ref args)) if args.url == "https://example.com")
+        );
+        assert!(Opts::try_parse_from(["httpie", "head", "https://example.com", "a=1"]).is_err());
+    }
+    #[test]
+    fn options_subcommand_works() {
+        let opts =
+            Opts::try_parse_from(["httpie", "options", "https://api.example.com/users"]).unwrap();
+        assert!(matches!(opts.subcmd, Some(SubCommand::Options(_))));
+    }
+    #[test]
+    fn parse_kv_pair_keeps_at_literal() {
+        assert_eq!(
+            parse_kv_pair("a@b=c").unwrap(),
+            KvPair {
+                key: "a@b".into(),
+                value: "c".into()
+            }
+        );
+        assert!(parse_kv_pair("bio@/etc/hostname").is_err());
+        let opts = Opts::try_parse_from(["httpie", "-q", "q=a@b", "get", "https://a.b"]).unwrap();
+        assert_eq!(opts.query[0].value, "a@b");
+    }
+    #[test]
+    fn body_file_works() {
+        let opts = Opts::try_parse_from(["httpie", "post", "https://a.b", "--body-file", "x.json"])
+            .unwrap();
+        assert!(
+            matches!(opts.subcmd, Some(SubCommand::Post(ref args)) if args.body.body_file == Some("x.json".into()))
+        );
+        let err = read_body_file(Path::new("/nonexistent/body.json")).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/body.json"));
+    }
+    #[test]
+    fn parse_header_works() {
+        assert!(parse_header("a").is_err());
+        assert!(parse_header("bad name:1").is_err());
+        assert!(parse_header("X-Test:bad\nvalue").is_err());
+        let (name, value) = parse_header("Authorization: Bearer abc:def").unwrap();
+        assert_eq!(name, header::AUTHORIZATION);
+        assert_eq!(value, "Bearer abc:def");
+        let (name, value) = parse_header("X-Empty:").unwrap();
+        assert_eq!(name, "x-empty");
+        assert_eq!(value, "");
+    }
+    #[test]
+    fn header_option_is_repeatable() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "-H",
+            "Accept: application/json",
+            "--header",
+            "X-A:1",
+            "get",
+            "https://example.com",
+        ])
+        .unwrap();
+        assert_eq!(opts.headers.len(), 2);
+        assert_eq!(opts.headers[0].0, header::ACCEPT);
+    }
+    #[test]
+    fn parse_basic_auth_works() {
+        assert!(parse_basic_auth("").is_err());
+        assert!(parse_basic_auth(":pass").is_err());
+        assert_eq!(
+            parse_basic_auth("user").unwrap(),
+            BasicAuth {
+                username: "user".into(),
+                password: None
+            }
+        );
+        assert_eq!(
+            parse_basic_auth("user:").unwrap(),
+            BasicAuth {
+                username: "user".into(),
+                password: Some("".into())
+            }
+        );
+        assert_eq!(
+            parse_basic_auth("user:pa:ss:").unwrap(),
+            BasicAuth {
+                username: "user".into(),
+                password: Some("pa:ss:".into())
+            }
+        );
+        let mut auth = parse_basic_auth("user").unwrap();
+        assert!(auth.prompt_password(false).is_err());
+        let mut auth = parse_basic_auth("user:").unwrap();
+        assert!(auth.prompt_password(false).is_ok());
+    }
+    #[test]
+    fn stdin_body_works() {
+        assert_eq!(read_body(&b"{\"a\":1}"[..]).unwrap(), b"{\"a\":1}");
+        assert!(Opts::try_parse_from(["httpie", "put", "https://a.b", "--stdin"]).is_ok());
+        assert!(Opts::try_parse_from([
+            "httpie",
+            "put",
+            "https://a.b",
+            "--stdin",
+            "--body-file",
+            "x"
+        ])
+        .is_err());
+    }
+    #[test]
+    fn body_field_from_str_works() {
+        assert_eq!(
+            "name=tom".parse::<BodyField>().unwrap(),
+            BodyField {
+                key: "name".into(),
+                value: Value::String("tom".into()),
+                file: None
+            }
+        );
+        assert_eq!(
+            "count:=5".parse::<BodyField>().unwrap(),
+            BodyField {
+                key: "count".into(),
+                value: serde_json::json!(5),
+                file: None
+            }
+        );
+        assert_eq!(
+            "tags:=[\"a\",\"b\"]".parse::<BodyField>().unwrap().value,
+            serde_json::json!(["a", "b"])
+        );
+        assert_eq!(
+            "active:=true".parse::<BodyField>().unwrap().value,
+            Value::Bool(true)
+        );
+        assert_eq!(
+            "none:=null".parse::<BodyField>().unwrap().value,
+            Value::Null
+        );
+        assert_eq!(
+            "url=http://a:=b".parse::<BodyField>().unwrap().value,
+            Value::String("http://a:=b".into())
+        );
+        assert!("count:=abc".parse::<BodyField>().is_err());
+        assert!("count".parse::<BodyField>().is_err());
+        // 文件在发送时才读取
+        assert_eq!(
+            "doc@/nonexistent/httpie-rust.txt"
+                .parse::<BodyField>()
+                .unwrap(),
+            BodyField {
+                key: "doc".into(),
+                value: Value::Null,
+                file: Some("/nonexistent/httpie-rust.txt".into())
+            }
+        );
+    }
+    #[test]
+    fn parse_request_item_works() {
+        assert_eq!(
+            parse_request_item("X-API-Key:abc").unwrap(),
+            RequestItem::Header(HeaderName::from_static("x-api-key"), "abc".parse().unwrap())
+        );
+        assert_eq!(
+            parse_request_item("X-Expr:a=b").unwrap(),
+            RequestItem::Header(HeaderName::from_static("x-expr"), "a=b".parse().unwrap())
+        );
+        assert_eq!(
+            parse_request_item("count:=5").unwrap(),
+            RequestItem::Field(BodyField {
+                key: "count".into(),
+                value: serde_json::json!(5),
+                file: None
+            })
+        );
+        assert_eq!(
+            parse_request_item("url=http://a.b").unwrap(),
+            RequestItem::Field(BodyField {
+                key: "url".into(),
+                value: Value::String("http://a.b".into()),
+                file: None
+            })
+        );
+        assert_eq!(
+            parse_request_item("expr=a:=b").unwrap(),
+            RequestItem::Field(BodyField {
+                key: "expr".into(),
+                value: Value::String("a:=b".into()),
+                file: None
+            })
+        );
+        assert!(parse_request_item("plain").is_err());
+        assert!(parse_request_item("bad name:1").is_err());
+    }
+    #[test]
+    fn timeout_must_be_positive_and_finite() {
+        for invalid in ["nan", "-1", "0", "inf", "1e30", "soon"] {
+            let args = ["httpie", "--timeout", invalid, "get", "https://a.b"];
+            assert!(Opts::try_parse_from(args).is_err(), "{}", invalid);
+        }
+        let opts = Opts::try_parse_from(["httpie", "--timeout", "0.5", "get", "https://a.b"]);
+        assert_eq!(opts.unwrap().timeout, Some(0.5));
+    }
+    #[test]
+    fn normalize_url_expands_shorthands() {
+        assert_eq!(
+            normalize_url("example.com", false).unwrap(),
+            "http://example.com"
+        );
+        assert_eq!(
+            normalize_url("example.com/api?a=1", true).unwrap(),
+            "https://example.com/api?a=1"
+        );
+        assert_eq!(
+            normalize_url("localhost:3000", false).unwrap(),
+            "http://localhost:3000"
+        );
+        assert_eq!(
+            normalize_url(":8080/path", false).unwrap(),
+            "http://localhost:8080/path"
+        );
+        assert_eq!(
+            normalize_url(":/path", false).unwrap(),
+            "http://localhost/path"
+        );
+        assert_eq!(normalize_url(":", false).unwrap(), "http://localhost");
+        assert_eq!(
+            normalize_url("[::1]:8080/path", false).unwrap(),
+            "http://[::1]:8080/path"
+        );
+        assert_eq!(
+            normalize_url("::1/path", true).unwrap(),
+            "https://[::1]/path"
+        );
+        assert_eq!(normalize_url("fe80::1", false).unwrap(), "http://[fe80::1]");
+        assert_eq!(
+            normalize_url("http://[::1]:8080/", false).unwrap(),
+            "http://[::1]:8080/"
+        );
+        assert_eq!(
+            normalize_url("/path", false).unwrap(),
+            "http://localhost/path"
+        );
+        assert_eq!(normalize_url("http://a.b/", true).unwrap(), "http://a.b/");
+        assert!(normalize_url(":99999", false).is_err());
+        assert!(normalize_url("http://[::1", false).is_err());
+    }
+    #[test]
+    fn config_fills_unset_options() {
+        let path = temp_path("config-test.toml");
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+        fs::write(
+            &path,
+            r#"
+headers = ["Accept: application/json", "X-Env: dev"]
+theme = "Solarized (dark)"
+timeout = 5
+follow_redirects = false
+"#,
+        )
+        .unwrap();
+        let parse = |args: &[&str]| {
+            let matches = Opts::into_app().try_get_matches_from(args).unwrap();
+            parse_opts_from(&matches, Some(path.clone())).unwrap()
+        };
+        let opts = parse(&["httpie", "get", "https://a.b"]);
+        assert_eq!(opts.theme, "Solarized (dark)");
+        assert_eq!(opts.timeout, Some(5.0));
+        assert!(opts.no_follow);
+        let headers = build_headers(&opts).unwrap();
+        assert_eq!(headers[header::ACCEPT], "application/json");
+        assert_eq!(headers["x-env"], "dev");
+
+        // 命令行参数优先
+        let opts = parse(&[
+            "httpie",
+            "-H",
+            "X-Env: prod",
+            "--theme",
+            DEFAULT_THEME,
+            "--timeout",
+            "1",
+            "--max-redirects",
+            "3",
+            "get",
+            "https://a.b",
+        ]);
+        assert_eq!(opts.theme, DEFAULT_THEME);
+        assert_eq!(opts.timeout, Some(1.0));
+        assert!(!opts.no_follow);
+        assert_eq!(build_headers(&opts).unwrap()["x-env"], "prod");
+
+        for invalid in [
+            "timeout = -1",
+            "timeout = 0",
+            "timeout = nan",
+            "timeout = inf",
+        ] {
+            fs::write(&path, invalid).unwrap();
+            let matches = Opts::into_app()
+                .try_get_matches_from(["httpie", "get", "https://a.b"])
+                .unwrap();
+            let err = parse_opts_from(&matches, Some(path.clone())).unwrap_err();
+            assert!(
+                err.to_string().starts_with("Invalid timeout"),
+                "{}",
+                invalid
+            );
+        }
+        fs::write(&path, "timeout = \"soon\"").unwrap();
+        let err = Config::load(&path).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid config file"));
+        fs::write(&path, "colour = true").unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn expand_vars_works() {
+        let lookup = |name: &str| match name {
+            "HOST" => Some("api.example.com".to_string()),
+            "TOKEN" => Some("s3cr$t".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_vars("https://${HOST}/v1?t=${TOKEN}", lookup, false).unwrap(),
+            "https://api.example.com/v1?t=s3cr$t"
+        );
+        assert_eq!(
+            expand_vars("cost $$5 and $HOME", lookup, false).unwrap(),
+            "cost $5 and $HOME"
+        );
+        assert_eq!(expand_vars("$${HOST}", lookup, false).unwrap(), "${HOST}");
+        assert_eq!(expand_vars("${HOST", lookup, false).unwrap(), "${HOST");
+        let err = expand_vars("https://${MISSING}/", lookup, false).unwrap_err();
+        assert!(err.to_string().contains("MISSING is not set"));
+        assert_eq!(
+            expand_vars("a${MISSING}b${HOST}", lookup, true).unwrap(),
+            "a${MISSING}bapi.example.com"
+        );
+    }
+    #[test]
+    fn expand_items_only_touches_string_fields() {
+        env::set_var("HTTPIE_RUST_TEST_NAME", "tom");
+        let items: Vec<RequestItem> = [
+            "X-A:${HTTPIE_RUST_TEST_NAME}",
+            "name=${HTTPIE_RUST_TEST_NAME}",
+            "raw:=[\"${X}\"]",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        let expanded = expand_items(&items, false).unwrap();
+        assert_eq!(expanded[0], items[0]);
+        assert_eq!(expanded[1], "name=tom".parse().unwrap());
+        assert_eq!(expanded[2], items[2]);
+        assert!(expand_items(&["a=${HTTPIE_RUST_TEST_UNSET}".parse().unwrap()], false).is_err());
+        assert!(parse_url("https://${HTTPIE_RUST_TEST_UNSET}/").is_ok());
+        assert!(parse_url("https://${HTTPIE_RUST_TEST_NAME}/").is_ok());
+    }
+    #[test]
+    fn conditional_request_headers() {
+        assert_eq!(parse_etag("abc").unwrap(), "\"abc\"");
+        assert_eq!(parse_etag("\"abc\"").unwrap(), "\"abc\"");
+        assert_eq!(parse_etag("W/\"abc\"").unwrap(), "W/\"abc\"");
+        assert_eq!(parse_etag("*").unwrap(), "*");
+        assert!(parse_http_date("yesterday").is_err());
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--etag",
+            "v1",
+            "--if-modified-since",
+            "Wed, 21 Oct 2015 07:28:00 GMT",
+            "get",
+            "https://a.b",
+        ])
+        .unwrap();
+        let headers = build_headers(&opts).unwrap();
+        assert_eq!(headers[header::IF_NONE_MATCH], "\"v1\"");
+        assert_eq!(
+            headers[header::IF_MODIFIED_SINCE],
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+    }
+    #[test]
+    fn ipv6_urls_keep_brackets() {
+        let req = Client::new().get("http://[::1]:8080/a").build().unwrap();
+        assert_eq!(req.url().host_str(), Some("[::1]"));
+        assert_eq!(req.url().port(), Some(8080));
+        assert_eq!(
+            curl_command(&req, &header::HeaderMap::new()),
+            "curl --globoff 'http://[::1]:8080/a'"
+        );
+    }
+    #[cfg(feature = "clipboard")]
+    #[test]
+    fn clipboard_conflicts_with_other_bodies() {
+        assert!(Opts::try_parse_from(["httpie", "post", "https://a.b", "--clipboard"]).is_ok());
+        for other in [
+            &["--stdin"][..],
+            &["--raw", "[]"],
+            &["--body-file", "a.json"],
+        ] {
+            let args = ["httpie", "post", "https://a.b", "--clipboard"]
+                .iter()
+                .chain(other)
+                .collect::<Vec<_>>();
+            assert!(Opts::try_parse_from(args).is_err());
+        }
+        let opts = Opts::try_parse_from(["httpie", "put", "https://a.b", "--clipboard"]).unwrap();
+        assert!(matches!(
+            opts.subcmd,
+            Some(SubCommand::Put(ref args)) if matches!(args.body.raw_body(), Some(RawBody::Clipboard))
+        ));
+    }
+    #[test]
+    fn range_sets_header() {
+        assert_eq!(parse_range("0-499").unwrap(), "bytes=0-499");
+        assert_eq!(parse_range("500-").unwrap(), "bytes=500-");
+        assert_eq!(parse_range("-500").unwrap(), "bytes=-500");
+        for bad in ["", "-", "5", "9-1", "a-b", "1-2-3", " 1-2", "0-1,4-5"] {
+            assert!(parse_range(bad).is_err(), "{}", bad);
+        }
+        let opts =
+            Opts::try_parse_from(["httpie", "--range", "0-99", "get", "https://a.b"]).unwrap();
+        assert_eq!(build_headers(&opts).unwrap()[header::RANGE], "bytes=0-99");
+        assert!(Opts::try_parse_from(["httpie", "--range", "x", "get", "https://a.b"]).is_err());
+    }
+    #[test]
+    fn query_and_cookie_values_keep_at_literal() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "-q",
+            "q=a@b",
+            "--cookie",
+            "c=@d",
+            "--data-urlencode",
+            "e=@f",
+            "post",
+            "https://a.b",
+        ])
+        .unwrap();
+        assert_eq!(opts.query[0].value, "a@b");
+        assert_eq!(opts.cookies[0].value, "@d");
+        assert_eq!(opts.data_urlencode[0].value, "@f");
+        let mut get = GET::try_parse_from(["get", "https://a.b", "to=a@b"]).unwrap();
+        assert_eq!(get.take_query().unwrap()[0].value, "a@b");
+    }
+    #[test]
+    fn accept_shorthands_expand_to_mime_types() {
+        for (short, full) in [
+            ("json", "application/json"),
+            ("xml", "application/xml"),
+            ("html", "text/html"),
+            ("text", "text/plain"),
+            ("yaml", "application/yaml"),
+            ("any", "*/*"),
+            ("application/vnd.api+json", "application/vnd.api+json"),
+            ("text/*;q=0.5", "text/*;q=0.5"),
+        ] {
+            assert_eq!(parse_accept(short).unwrap(), full);
+        }
+        assert!(parse_accept("jsn").is_err());
+        let opts = Opts::try_parse_from(["httpie", "--accept-json", "get", "https://a.b"]).unwrap();
+        assert_eq!(
+            build_headers(&opts).unwrap()[header::ACCEPT],
+            "application/json"
+        );
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--accept",
+            "xml",
+            "-H",
+            "Accept:text/csv",
+            "get",
+            "https://a.b",
+        ])
+        .unwrap();
+        assert_eq!(build_headers(&opts).unwrap()[header::ACCEPT], "text/csv");
+        assert!(Opts::try_parse_from([
+            "httpie",
+            "--accept",
+            "xml",
+            "--accept-json",
+            "get",
+            "https://a.b"
+        ])
+        .is_err());
+    }
+    #[test]
+    fn parse_resolve_works() {
+        assert_eq!(
+            parse_resolve("Example.com:443:127.0.0.1").unwrap(),
+            ("example.com".into(), "127.0.0.1:443".parse().unwrap())
+        );
+        assert_eq!(
+            parse_resolve("a.b:80:[::1]").unwrap(),
+            ("a.b".into(), "[::1]:80".parse().unwrap())
+        );
+        assert_eq!(
+            parse_resolve("a.b:80:::1").unwrap().1,
+            "[::1]:80".parse().unwrap()
+        );
+        for bad in [
+            "a.b",
+            "a.b:80",
+            ":80:127.0.0.1",
+            "a.b:x:127.0.0.1",
+            "a.b:80:host",
+        ] {
+            assert!(parse_resolve(bad).is_err(), "{}", bad);
+        }
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--resolve",
+            "a.b:80:127.0.0.1",
+            "--resolve",
+            "c.d:80:10.0.0.1",
+            "get",
+            "http://a.b",
+        ])
+        .unwrap();
+        assert_eq!(opts.resolve.len(), 2);
+        assert!(build_client(&opts).is_ok());
+    }
+    #[test]
+    fn parse_interface_works() {
+        assert_eq!(
+            parse_interface("127.0.0.1").unwrap(),
+            IpAddr::from([127, 0, 0, 1])
+        );
+        assert_eq!(
+            parse_interface("::1").unwrap(),
+            "::1".parse::<IpAddr>().unwrap()
+        );
+        assert!(parse_interface("eth0").is_err());
+        let opts =
+            Opts::try_parse_from(["httpie", "--interface", "127.0.0.1", "get", "http://a.b"])
+                .unwrap();
+        assert_eq!(opts.interface, Some(IpAddr::from([127, 0, 0, 1])));
+        assert!(build_client(&opts).is_ok());
+    }
+    #[test]
+    fn graphql_envelope_wraps_query_and_variables() {
+        let query = "query User($id: ID!) { user(id: $id) { name } }";
+        assert_eq!(
+            graphql_envelope(query, None, None),
+            serde_json::json!({ "query": query })
+        );
+        let variables = parse_variables(r#"{"id": "42"}"#).unwrap();
+        assert_eq!(
+            graphql_envelope(query, Some(&variables), Some("User")),
+            serde_json::json!({ "query": query, "variables": { "id": "42" }, "operationName": "User" })
+        );
+        assert!(parse_variables("[1]").is_err());
+        assert!(parse_variables("{").is_err());
+    }
+    #[test]
+    fn graphql_reads_query_from_argument_or_stdin() {
+        let parse = |args: &[&str]| match Opts::try_parse_from(args).unwrap().subcmd {
+            Some(SubCommand::Graphql(args)) => args,
+            other => panic!("unexpected {:?}", other),
+        };
+        let body = |raw: RawBody| match raw {
+            RawBody::Json(text) => serde_json::from_str::<Value>(&text).unwrap(),
+            other => panic!("unexpected {:?}", other),
+        };
+        let args = parse(&[
+            "httpie",
+            "graphql",
+            "a.b",
+            "{ me { id } }",
+            "--variables",
+            r#"{"a":1}"#,
+        ]);
+        assert_eq!(
+            body(args.body(None::<&[u8]>).unwrap()),
+            serde_json::json!({ "query": "{ me { id } }", "variables": { "a": 1 } })
+        );
+        let args = parse(&["httpie", "graphql", "a.b"]);
+        assert_eq!(
+            body(args.body(Some(&b"{ viewer { login } }\n"[..])).unwrap()),
+            serde_json::json!({ "query": "{ viewer { login } }\n" })
+        );
+        assert!(args.body(None::<&[u8]>).is_err());
+        assert!(args.body(Some(&b"  \n"[..])).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! httpie-rust 的核心逻辑：请求项解析、请求发送和响应输出。
+//!
+//! 命令行程序只是 [`run`] 的一层包装；其他程序可以直接构造 [`RequestSpec`]，
+//! 通过 [`execute_request`] 发送，再用 [`Printer`] 输出响应。
+
+mod cli;
+mod history;
+mod pager;
+mod print;
+mod request;
+mod session;
+mod tls;
+
+pub use cli::{
+    expand_env, normalize_url, parse_kv_pair, parse_opts, parse_url, BodyField, Config, KvPair,
+    Opts, RequestItem,
+};
+pub use pager::mark_interrupted;
+pub use print::{Printer, Sections};
+pub use request::{execute_request, RequestSpec, SavedRequest};
+
+use anyhow::{anyhow, Result};
+use clap::{ErrorKind, IntoApp};
+use colored::Colorize;
+use futures_util::{stream, StreamExt};
+use reqwest::{Method, StatusCode};
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
+use tracing::Level;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+
+use crate::cli::{data_body, expand_items, parse_method, ColorChoice, RawBody, SubCommand};
+use crate::history::{history_path, read_history};
+use crate::print::theme_names;
+use crate::request::{build_client, friendly_error, status_exit_code, HttpRequest};
+
+// auto 模式下，NO_COLOR 存在且非空 (https://no-color.org) 或 stdout 不是终端时关闭颜色
+fn color_enabled(choice: ColorChoice, no_color: bool, env: Option<OsString>, tty: bool) -> bool {
+    match choice {
+        _ if no_color => false,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => tty && env.is_none_or(|v| v.is_empty()),
+    }
+}
+
+// --log-level 优先，否则读取 RUST_LOG，两者都没有时不输出日志；日志只写到 stderr
+fn init_tracing(level: Option<Level>) {
+    let filter = match level {
+        Some(level) => EnvFilter::default().add_directive(LevelFilter::from_level(level).into()),
+        None => match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return,
+        },
+    };
+    // 同一进程里多次调用 run 时只有第一次生效
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .try_init();
+}
+
+/// 执行一次命令行调用，返回进程退出码
+pub async fn run(opts: Opts) -> Result<i32> {
+    init_tracing(opts.log_level);
+    if opts.list_themes {
+        for name in theme_names() {
+            println!("{}", name);
+        }
+        return Ok(0);
+    }
+    colored::control::set_override(
+        !opts.json
+            && color_enabled(
+                opts.color,
+                opts.no_color,
+                env::var_os("NO_COLOR"),
+                io::stdout().is_terminal(),
+            ),
+    );
+    if let Some(SubCommand::Format(ref args)) = opts.subcmd {
+        Printer::from_opts(&opts).print_file(&args.path)?;
+        return Ok(0);
+    }
+    if let Some(SubCommand::History(ref args)) = opts.subcmd {
+        for entry in read_history(&history_path()?, args.limit)? {
+            println!("{}", entry);
+        }
+        return Ok(0);
+    }
+    let client = build_client(&opts)?;
+    let http = HttpRequest::new(client, &opts)?;
+    let subcmd = match opts.subcmd {
+        Some(ref subcmd) => subcmd,
+        None => Opts::into_app()
+            .error(ErrorKind::MissingSubcommand, "A subcommand is required")
+            .exit(),
+    };
+    if let SubCommand::Diff(args) = subcmd {
+        let resolve = |url: &str| -> Result<String> {
+            Ok(normalize_url(
+                &expand_env(url, opts.allow_unset)?,
+                opts.https,
+            )?)
+        };
+        let (left, right) = (resolve(&args.left)?, resolve(&args.right)?);
+        return http
+            .diff(&left, &right, args.include_headers)
+            .await
+            .map_err(|e| friendly_error(e, &opts));
+    }
+    let replayed = match subcmd {
+        SubCommand::Replay(args) => args.request_items()?,
+        _ => Vec::new(),
+    };
+    let (method, items, raw): (_, &[RequestItem], _) = match subcmd {
+        SubCommand::Get(_) => (Method::GET, &[], None),
+        SubCommand::Post(args) => (Method::POST, &args.items, args.body.raw_body()),
+        SubCommand::Put(args) => (Method::PUT, &args.items, args.body.raw_body()),
+        SubCommand::Patch(args) => (Method::PATCH, &args.items, args.body.raw_body()),
+        SubCommand::Delete(args) => (Method::DELETE, &args.items, None),
+        SubCommand::Head(_) => (Method::HEAD, &[], None),
+        SubCommand::Options(_) => (Method::OPTIONS, &[], None),
+        SubCommand::Request(args) => (args.method.clone(), &args.items, args.body.raw_body()),
+        SubCommand::Graphql(args) => (
+            Method::POST,
+            &[],
+            Some(args.body((!opts.ignore_stdin).then(io::stdin))?),
+        ),
+        SubCommand::Replay(args) => (
+            match args.method {
+                Some(ref method) => method.clone(),
+                None => parse_method(&args.saved.method)?,
+            },
+            &replayed,
+            args.saved.body.clone().map(RawBody::Text),
+        ),
+        SubCommand::Format(_) | SubCommand::History(_) | SubCommand::Diff(_) => {
+            unreachable!("handled before sending requests")
+        }
+    };
+    if opts.ignore_stdin && matches!(raw, Some(RawBody::Stdin)) {
+        return Err(anyhow!("--stdin cannot be combined with --ignore-stdin"));
+    }
+    let raw = match raw {
+        _ if opts.data.is_empty() => raw,
+        None => Some(data_body(&opts.data, opts.form)),
+        Some(_) => {
+            return Err(anyhow!(
+                "--data cannot be combined with --body-file, --stdin, --raw or --clipboard"
+            ))
+        }
+    };
+    let items = expand_items(items, opts.allow_unset)?;
+    let urls = subcmd.urls();
+    let (fail_fast, parallel, unordered) = match subcmd {
+        SubCommand::Get(args) => (args.fail_fast, args.parallel, args.unordered),
+        _ => (false, None, false),
+    };
+    let resolve = |url: &str| -> Result<String> {
+        Ok(normalize_url(
+            &expand_env(url, opts.allow_unset)?,
+            opts.https,
+        )?)
+    };
+    // 多个 URL 时单个失败只输出错误并继续，最后汇总
+    let multiple = urls.len() > 1;
+    let mut outcome = Outcome {
+        check_status: opts.check_status || opts.body_only_on_error,
+        stop_on_error: !multiple || fail_fast,
+        code: 0,
+        failed: 0,
+    };
+    match parallel {
+        Some(n) if multiple && !opts.offline => {
+            if opts.download || opts.output.is_some() {
+                return Err(anyhow!(
+                    "--parallel cannot be combined with -o or --download"
+                ));
+            }
+            let (http, resolve) = (&http, &resolve);
+            let fetches = stream::iter(urls)
+                .map(|url| async move { (url, async { http.fetch(&resolve(url)?).await }.await) });
+            let mut results = if unordered {
+                fetches.buffer_unordered(n.max(1)).boxed_local()
+            } else {
+                fetches.buffered(n.max(1)).boxed_local()
+            };
+            while let Some((url, fetched)) = results.next().await {
+                print_divider(url);
+                let result = match fetched {
+                    Ok(exchange) => http.respond(exchange, false).await,
+                    Err(e) => Err(e),
+                };
+                outcome.record(result.map_err(|e| friendly_error(e, &opts)))?;
+            }
+        }
+        _ => {
+            for url in urls {
+                if multiple {
+                    print_divider(url);
+                }
+                let result = match (resolve(url), opts.repeat) {
+                    (Ok(url), Some(times)) if !opts.offline => {
+                        http.repeat(times, method.clone(), &url, &items, &raw).await
+                    }
+                    (Ok(url), _) => http.execute(method.clone(), &url, &items, &raw).await,
+                    (Err(e), _) => Err(e),
+                };
+                outcome.record(result.map_err(|e| friendly_error(e, &opts)))?;
+            }
+        }
+    }
+    if multiple {
+        println!(
+            "{} of {} requests succeeded",
+            urls.len() - outcome.failed,
+            urls.len()
+        );
+    }
+    Ok(if outcome.failed > 0 { 1 } else { outcome.code })
+}
+
+fn print_divider(url: &str) {
+    println!("{}", format!("──── {} ────", url).bold());
+}
+
+// 汇总多个 URL 的结果，--check-status 时取最严重的退出码
+struct Outcome {
+    check_status: bool,
+    stop_on_error: bool,
+    code: i32,
+    failed: usize,
+}
+
+impl Outcome {
+    fn record(&mut self, result: Result<StatusCode>) -> Result<()> {
+        match result {
+            Ok(status) if self.check_status => self.code = self.code.max(status_exit_code(status)),
+            Ok(_) => {}
+            Err(e) if self.stop_on_error => return Err(e),
+            Err(e) => {
+                self.failed += 1;
+                eprintln!("{}", format!("Error: {:#}", e).red());
+            }
+        }
+        Ok(())
+    }
+}
+
+// 仅在 cargo test 时才编译
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+#[cfg(test)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print::{highlight, DEFAULT_THEME};
+    use clap::Parser;
+
+    #[test]
+    fn color_can_be_disabled() {
+        assert!(color_enabled(ColorChoice::Auto, false, None, true));
+        assert!(color_enabled(
+            ColorChoice::Auto,
+            false,
+            Some("".into()),
+            true
+        ));
+        assert!(!color_enabled(
+            ColorChoice::Auto,
+            false,
+            Some("1".into()),
+            true
+        ));
+        assert!(!color_enabled(ColorChoice::Auto, true, None, true));
+        let json = "{\"a\": 1}\n";
+        assert_eq!(highlight(json, "json", DEFAULT_THEME, false), json);
+        assert!(highlight(json, "json", DEFAULT_THEME, true).contains("\x1b["));
+    }
+    #[test]
+    fn color_follows_tty_unless_forced() {
+        assert!(!color_enabled(ColorChoice::Auto, false, None, false));
+        assert!(color_enabled(ColorChoice::Always, false, None, false));
+        assert!(color_enabled(
+            ColorChoice::Always,
+            false,
+            Some("1".into()),
+            false
+        ));
+        assert!(!color_enabled(ColorChoice::Always, true, None, true));
+        assert!(!color_enabled(ColorChoice::Never, false, None, true));
+        let opts =
+            Opts::try_parse_from(["httpie", "--color", "always", "get", "https://a.b"]).unwrap();
+        assert_eq!(opts.color, ColorChoice::Always);
+        assert!(
+            Opts::try_parse_from(["httpie", "--color", "sometimes", "get", "https://a.b"]).is_err()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! 缓冲输出的分页显示
+
+use std::env;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::cli::Opts;
+
+// 写文件或显示下载进度时不分页
+pub(crate) fn pager_enabled(opts: &Opts, tty: bool) -> bool {
+    !opts.no_pager && (opts.pager || tty) && opts.output.is_none() && !opts.download
+}
+
+// Ctrl-C 中断后为 true，此时不再等待分页程序退出
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// 标记进程已被 Ctrl-C 中断，之后丢弃 `run` 时不再等待分页程序退出
+pub fn mark_interrupted() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// 缓冲输出的 body 写入分页程序的 stdin，写完后调用 finish 等待分页程序退出
+pub(crate) struct Pager {
+    stdin: Option<io::PipeWriter>,
+    child: tokio::process::Child,
+}
+
+impl Pager {
+    // 与 git 一致优先用 $PAGER，LESS 未设置时用 FRX，内容不足一屏时直接输出；
+    // 找不到分页程序时返回 None，直接输出
+    pub(crate) fn start() -> Option<Self> {
+        let command = env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| "less -R".to_string());
+        Self::spawn(&command)
+    }
+    fn spawn(command: &str) -> Option<Self> {
+        let mut args = command.split_whitespace();
+        let program = args.next()?;
+        if program == "cat" {
+            return None;
+        }
+        let (reader, writer) = io::pipe().ok()?;
+        let mut cmd = tokio::process::Command::new(program);
+        cmd.args(args).stdin(reader);
+        if env::var_os("LESS").is_none() {
+            cmd.env("LESS", "FRX");
+        }
+        let child = cmd.spawn().ok()?;
+        // 之前输出的 status 和 headers 要先于分页内容显示
+        io::stdout().flush().ok()?;
+        Some(Self {
+            stdin: Some(writer),
+            child,
+        })
+    }
+    // 关闭写端让分页程序读到 EOF，等待期间仍可以被 Ctrl-C 取消
+    pub(crate) async fn finish(mut self) {
+        self.stdin.take();
+        let _ = self.child.wait().await;
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let stdin = self.stdin.as_mut().ok_or(io::ErrorKind::BrokenPipe)?;
+        match stdin.write(buf) {
+            // 用户提前退出分页程序时丢弃剩余的输出
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                self.stdin.take();
+                Ok(buf.len())
+            }
+            result => result,
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        // 中断后分页程序可能还在等待用户操作，通知它退出即可
+        let pid = match self.child.id() {
+            Some(pid) if INTERRUPTED.load(Ordering::SeqCst) => pid,
+            _ => return,
+        };
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (pid, self.child.start_kill());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Opts;
+    use clap::Parser;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pager_exiting_early_drops_the_rest() {
+        let mut pager = Pager::spawn("true").unwrap();
+        // 超过管道缓冲区，分页程序退出后写入一定会遇到 EPIPE
+        pager.write_all(&vec![b'x'; 1 << 20]).unwrap();
+        pager.finish().await;
+        assert!(Pager::spawn("cat").is_none());
+    }
+    #[test]
+    fn pager_only_on_terminals_unless_forced() {
+        let parse = |args: &[&str]| Opts::try_parse_from(args).unwrap();
+        assert!(pager_enabled(&parse(&["httpie", "get", "a.b"]), true));
+        assert!(!pager_enabled(&parse(&["httpie", "get", "a.b"]), false));
+        assert!(pager_enabled(
+            &parse(&["httpie", "--pager", "get", "a.b"]),
+            false
+        ));
+        assert!(!pager_enabled(
+            &parse(&["httpie", "--no-pager", "get", "a.b"]),
+            true
+        ));
+        assert!(!pager_enabled(
+            &parse(&["httpie", "--pager", "--no-pager", "get", "a.b"]),
+            true
+        ));
+        assert!(!pager_enabled(
+            &parse(&["httpie", "-o", "out.json", "get", "a.b"]),
+            true
+        ));
+    }
+}
@@ -0,0 +1,1756 @@
+//! 响应输出：status、headers 和 body 的格式化与高亮，以及保存到文件
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use encoding_rs::{Encoding, UTF_8};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use futures_util::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+use reqwest::{header, Method, Request, Response, StatusCode};
+use serde_json::{Map, Value};
+use similar::TextDiff;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Style, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+use tokio::io::AsyncWriteExt;
+use url::{form_urlencoded, Url};
+
+use crate::cli::Opts;
+use crate::pager::{pager_enabled, Pager};
+use crate::request::DNS_ELAPSED;
+
+// Client 的默认请求头在发送时才合并，这里提前合并以便在 -v 时完整展示
+pub(crate) fn request_headers(req: &Request, defaults: &header::HeaderMap) -> header::HeaderMap {
+    let mut headers = req.headers().clone();
+    for (name, value) in defaults.iter() {
+        if !headers.contains_key(name) {
+            headers.insert(name, value.clone());
+        }
+    }
+    headers
+}
+
+// 请求行随请求头一起输出
+pub(crate) fn print_request(
+    req: &Request,
+    defaults: &header::HeaderMap,
+    headers: bool,
+    body: bool,
+) {
+    println!("{}", "[request]".bold().truecolor(164, 111, 164));
+    if headers {
+        let method = req.method().to_string().truecolor(117, 157, 255);
+        let url = req.url().to_string().truecolor(67, 95, 164);
+        println!("{} {}", method, url);
+        for (name, value) in request_headers(req, defaults).iter() {
+            println!("{}: {:?}", name.to_string().truecolor(157, 173, 212), value)
+        }
+    }
+    if let (true, Some(body)) = (body, req.body().and_then(|b| b.as_bytes())) {
+        println!("{}", String::from_utf8_lossy(body));
+    }
+}
+
+// 只含安全字符时原样输出，否则用单引号包裹，内部的单引号写成 '\''
+fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,%+".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+pub(crate) fn curl_command(req: &Request, defaults: &header::HeaderMap) -> String {
+    let mut args = vec!["curl".to_string()];
+    match *req.method() {
+        Method::GET => {}
+        Method::HEAD => args.push("-I".to_string()),
+        ref method => args.extend(["-X".to_string(), method.to_string()]),
+    }
+    for (name, value) in request_headers(req, defaults).iter() {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        args.extend([
+            "-H".to_string(),
+            shell_quote(&format!("{}: {}", name, value)),
+        ]);
+    }
+    if let Some(body) = req.body().and_then(|b| b.as_bytes()) {
+        args.extend([
+            "--data-binary".to_string(),
+            shell_quote(&String::from_utf8_lossy(body)),
+        ]);
+    }
+    // IPv6 地址的方括号会被 curl 当作 URL 通配符
+    if req.url().as_str().contains('[') {
+        args.push("--globoff".to_string());
+    }
+    args.push(shell_quote(req.url().as_str()));
+    args.join(" ")
+}
+
+pub(crate) fn format_timing(headers: Duration, total: Duration) -> String {
+    format!(
+        "{} ms (headers {} ms)",
+        total.as_millis(),
+        headers.as_millis()
+    )
+}
+
+pub(crate) fn format_latencies(latencies: &[Duration]) -> String {
+    let (min, max) = match (latencies.iter().min(), latencies.iter().max()) {
+        (Some(min), Some(max)) => (min, max),
+        _ => return "0 requests".to_string(),
+    };
+    let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    format!(
+        "{} requests, min {} ms, max {} ms, mean {} ms",
+        latencies.len(),
+        min.as_millis(),
+        max.as_millis(),
+        mean.as_millis()
+    )
+}
+
+pub(crate) fn print_timing(headers: Duration, total: Duration) {
+    println!(
+        "{} {}",
+        "[timing]".bold().truecolor(164, 111, 164),
+        format_timing(headers, total)
+    );
+}
+
+// 2xx 绿色，3xx 黄色，4xx/5xx 红色，其余保持原来的蓝色
+fn status_color(status: StatusCode) -> (u8, u8, u8) {
+    match status.as_u16() {
+        200..=299 => (98, 192, 120),
+        300..=399 => (222, 184, 84),
+        400..=599 => (226, 96, 96),
+        _ => (117, 157, 255),
+    }
+}
+
+fn print_status(res: &Response) {
+    println!("{}", "[status]".bold().truecolor(164, 111, 164));
+    let version = format!("{:?}", res.version()).truecolor(67, 95, 164);
+    let (r, g, b) = status_color(res.status());
+    let status = format!("{}", res.status()).truecolor(r, g, b);
+    // 206 时附上服务器实际返回的区间
+    match res.headers().get(header::CONTENT_RANGE) {
+        Some(range) if res.status() == StatusCode::PARTIAL_CONTENT => {
+            let range = String::from_utf8_lossy(range.as_bytes()).into_owned();
+            println!("{} {} {}", version, status, range.truecolor(157, 173, 212));
+        }
+        _ => println!("{} {}", version, status),
+    }
+}
+
+// Set-Cookie 拆成 name=value 和逐行缩进的属性，值本身可能包含 =
+fn expand_set_cookie(value: &str) -> String {
+    let mut parts = value.split(';').map(str::trim).filter(|p| !p.is_empty());
+    let mut lines = vec![parts.next().unwrap_or_default().to_string()];
+    lines.extend(parts.map(|attr| match attr.split_once('=') {
+        Some((key, value)) => format!("    {}: {}", key.trim(), value.trim()),
+        None => format!("    {}", attr),
+    }));
+    lines.join("\n")
+}
+
+// 返回 (名称加冒号, 值)，排序时保持同名响应头的原始顺序，并按最长名称补齐
+fn header_lines(
+    headers: &header::HeaderMap,
+    sort: bool,
+    expand_cookies: bool,
+) -> Vec<(String, String)> {
+    let mut lines: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| {
+            let value = match value.to_str() {
+                Ok(cookie) if expand_cookies && name == header::SET_COOKIE => {
+                    expand_set_cookie(cookie)
+                }
+                _ => format!("{:?}", value),
+            };
+            (format!("{}:", name), value)
+        })
+        .collect();
+    if sort {
+        lines.sort_by(|a, b| a.0.cmp(&b.0));
+        let width = lines.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, _) in lines.iter_mut() {
+            *name = format!("{:width$}", name, width = width);
+        }
+    }
+    lines
+}
+
+fn print_headers(headers: &header::HeaderMap, sort: bool, expand_cookies: bool) {
+    println!("{}", "[headers]".bold().truecolor(164, 111, 164));
+    for (name, value) in header_lines(headers, sort, expand_cookies) {
+        println!("{} {}", name.truecolor(157, 173, 212), value)
+    }
+}
+
+pub(crate) const DEFAULT_THEME: &str = "base16-eighties.dark";
+
+pub(crate) fn theme_names() -> Vec<String> {
+    ThemeSet::load_defaults().themes.into_keys().collect()
+}
+
+pub(crate) fn parse_theme(name: &str) -> Result<String, anyhow::Error> {
+    let names = theme_names();
+    if names.iter().any(|n| n == name) {
+        Ok(name.into())
+    } else {
+        Err(anyhow!(
+            "Unknown theme {}, available themes: {}",
+            name,
+            names.join(", ")
+        ))
+    }
+}
+
+pub(crate) fn highlight(content: &str, ext: &str, theme: &str, color: bool) -> String {
+    if !color {
+        return content.to_string();
+    }
+    let ps = SyntaxSet::load_defaults_newlines();
+    let ts = ThemeSet::load_defaults();
+    let syntax = ps.find_syntax_by_extension(ext).unwrap();
+    let theme = &ts.themes[theme];
+    let mut h = HighlightLines::new(syntax, theme);
+    let mut output = String::new();
+    for line in LinesWithEndings::from(content) {
+        let ranges: Vec<(Style, &str)> = h.highlight(line, &ps);
+        output.push_str(&as_24_bit_terminal_escaped(&ranges[..], false));
+    }
+    output
+}
+
+// 只比较 type/subtype，忽略 charset 等参数；扩展名需在 SyntaxSet::load_defaults_newlines 中存在
+fn syntax_extension(m: &Mime) -> Option<&'static str> {
+    match (m.type_().as_str(), m.subtype().as_str()) {
+        ("application", "json") => Some("json"),
+        ("text", "html") => Some("html"),
+        ("text", "css") => Some("css"),
+        ("application", "javascript") => Some("js"),
+        ("application", "xml") | ("text", "xml") => Some("xml"),
+        ("application", "x-yaml") | ("application", "yaml") | ("text", "yaml") => Some("yaml"),
+        _ => None,
+    }
+}
+
+fn is_binary(m: &Mime) -> bool {
+    let textual_suffix = matches!(m.suffix().map(|s| s.as_str()), Some("json" | "xml"));
+    !(m.type_() == mime::TEXT
+        || textual_suffix
+        || syntax_extension(m).is_some()
+        || matches!(
+            m.essence_str(),
+            "application/x-www-form-urlencoded" | "application/ecmascript"
+        ))
+}
+
+fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+// 二进制内容不直接输出到终端，只显示大小和类型
+fn print_binary_body(out: &mut impl Write, len: usize, kind: &str) -> io::Result<()> {
+    writeln!(out, "<binary data: {}, {}>", format_size(len), kind)
+}
+
+// 与 reqwest 的 text() 一致：按 charset 解码，未知编码按 UTF-8 处理
+fn text_encoding(m: &Mime) -> &'static Encoding {
+    m.get_param("charset")
+        .and_then(|c| Encoding::for_label(c.as_str().as_bytes()))
+        .unwrap_or(UTF_8)
+}
+
+fn decode_text(body: &[u8], m: &Mime) -> String {
+    text_encoding(m).decode(body).0.into_owned()
+}
+
+// 一条 Server-Sent Event，data 的多行以 \n 连接
+#[derive(Debug, Default, PartialEq)]
+struct SseEvent {
+    event: Option<String>,
+    data: String,
+    id: Option<String>,
+}
+
+// 按行解析 text/event-stream，空行结束一个事件，行可能跨多个数据块
+#[derive(Default)]
+struct SseParser {
+    buf: String,
+    current: SseEvent,
+    has_data: bool,
+}
+
+impl SseParser {
+    fn feed(&mut self, text: &str) -> Vec<SseEvent> {
+        self.buf.push_str(text);
+        let mut events = Vec::new();
+        while let Some(end) = self.buf.find('\n') {
+            let line: String = self.buf.drain(..=end).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                // 没有 data 的事件按规范直接丢弃
+                let event = std::mem::take(&mut self.current);
+                if std::mem::take(&mut self.has_data) {
+                    events.push(event);
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.current.event = Some(value.to_string()),
+                "id" => self.current.id = Some(value.to_string()),
+                "data" => {
+                    if self.has_data {
+                        self.current.data.push('\n');
+                    }
+                    self.current.data.push_str(value);
+                    self.has_data = true;
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+// 连接保持打开，逐个输出事件直到服务器关闭或 Ctrl-C
+async fn print_events(res: Response) -> Result<()> {
+    let mut decoder = UTF_8.new_decoder();
+    let mut parser = SseParser::default();
+    let mut stream = res.bytes_stream();
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        text.clear();
+        text.reserve(
+            decoder
+                .max_utf8_buffer_length(chunk.len())
+                .unwrap_or(chunk.len() * 3),
+        );
+        let _ = decoder.decode_to_string(&chunk, &mut text, false);
+        for event in parser.feed(&text) {
+            let name = event.event.as_deref().unwrap_or("message");
+            let label = format!("event: {}", name).truecolor(117, 157, 255);
+            match event.id {
+                Some(ref id) => println!(
+                    "{} {}",
+                    label,
+                    format!("(id {})", id).truecolor(157, 173, 212)
+                ),
+                None => println!("{}", label),
+            }
+            println!("{}", event.data);
+            io::stdout().flush()?;
+        }
+    }
+    Ok(())
+}
+
+// 按块解码并立即输出，跨块的多字节字符由 decoder 拼接；返回收到的字节数
+async fn print_stream(res: Response, encoding: &'static Encoding) -> Result<usize> {
+    let mut decoder = encoding.new_decoder();
+    let mut stdout = io::stdout();
+    let mut stream = res.bytes_stream();
+    let mut received = 0;
+    let mut text = String::new();
+    loop {
+        let chunk = stream.next().await.transpose()?;
+        let bytes = chunk.as_deref().unwrap_or_default();
+        received += bytes.len();
+        text.clear();
+        text.reserve(
+            decoder
+                .max_utf8_buffer_length(bytes.len())
+                .unwrap_or(bytes.len() * 3),
+        );
+        let _ = decoder.decode_to_string(bytes, &mut text, chunk.is_none());
+        stdout.write_all(text.as_bytes())?;
+        stdout.flush()?;
+        if chunk.is_none() {
+            return Ok(received);
+        }
+    }
+}
+
+// 返回 [meta] 内容以及 Content-Length 与实际大小是否不一致
+fn format_meta(declared: Option<u64>, received: usize) -> (String, bool) {
+    match declared {
+        Some(len) => (
+            format!("content-length: {}, received: {} bytes", len, received),
+            len != received as u64,
+        ),
+        None => (
+            format!("content-length: none, received: {} bytes", received),
+            false,
+        ),
+    }
+}
+
+fn print_meta(declared: Option<u64>, received: usize) {
+    println!("{}", "[meta]".bold().truecolor(164, 111, 164));
+    match format_meta(declared, received) {
+        (line, true) => println!("{}", line.yellow()),
+        (line, false) => println!("{}", line),
+    }
+}
+
+// 按 . 分隔逐级取值，对象按键、数组按下标，空路径返回整个值
+fn select_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+// 无法解析的 JSON 原样输出
+fn prettify_json(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+/// 响应中需要输出的部分，默认全部输出，对应 `--headers` / `--body`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sections {
+    pub status: bool,
+    pub headers: bool,
+    pub body: bool,
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Self {
+            status: true,
+            headers: true,
+            body: true,
+        }
+    }
+}
+
+impl Sections {
+    pub(crate) fn from_opts(opts: &Opts) -> Self {
+        match opts.print {
+            Some(spec) => Self {
+                status: spec.response_headers,
+                headers: spec.response_headers,
+                body: spec.response_body,
+            },
+            None => Self {
+                status: !opts.body_only && !opts.body_only_on_error,
+                headers: !opts.body_only && !opts.body_only_on_error,
+                body: !opts.headers_only,
+            },
+        }
+    }
+}
+
+// --print 的取值，如 HBhb；未指定时相当于 hb，-v 时相当于 HBhb
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct PrintSpec {
+    pub(crate) request_headers: bool,
+    pub(crate) request_body: bool,
+    pub(crate) response_headers: bool,
+    pub(crate) response_body: bool,
+}
+
+impl FromStr for PrintSpec {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(anyhow!("--print expects one or more of H, B, h, b"));
+        }
+        if let Some(c) = s.chars().find(|c| !"HBhb".contains(*c)) {
+            return Err(anyhow!(
+                "Invalid --print character {:?}, expected H, B, h or b",
+                c
+            ));
+        }
+        Ok(Self {
+            request_headers: s.contains('H'),
+            request_body: s.contains('B'),
+            response_headers: s.contains('h'),
+            response_body: s.contains('b'),
+        })
+    }
+}
+
+/// 按 Content-Type 高亮输出响应
+pub struct Printer {
+    pretty: bool,
+    theme: String,
+    meta: bool,
+    max_body_size: Option<u64>,
+    sort_headers: bool,
+    expand_cookies: bool,
+    select: Option<String>,
+    raw: bool,
+    stream: bool,
+    decode: bool,
+    filter: Option<String>,
+    body_highlight: bool,
+    pager: bool,
+}
+
+impl Printer {
+    /// `pretty` 控制是否格式化 JSON，`theme` 为 syntect 主题名，未知的主题回退到默认主题
+    pub fn new(pretty: bool, theme: &str) -> Self {
+        let theme = if theme_names().iter().any(|name| name == theme) {
+            theme
+        } else {
+            DEFAULT_THEME
+        };
+        Self {
+            pretty,
+            theme: theme.into(),
+            meta: false,
+            max_body_size: None,
+            sort_headers: false,
+            expand_cookies: false,
+            select: None,
+            raw: false,
+            stream: false,
+            decode: false,
+            filter: None,
+            body_highlight: true,
+            pager: false,
+        }
+    }
+    /// 缓冲输出的 body 交给 `$PAGER`（默认 `less -R`）显示，流式输出和 SSE 不分页
+    pub fn with_pager(mut self, pager: bool) -> Self {
+        self.pager = pager;
+        self
+    }
+    /// 关闭 body 的语法高亮，status 和 headers 仍按全局设置着色
+    pub fn with_body_highlight(mut self, highlight: bool) -> Self {
+        self.body_highlight = highlight;
+        self
+    }
+    /// 把 body 交给外部命令处理，输出命令的 stdout，看起来像 JSON 时照常高亮
+    pub fn with_filter(mut self, command: Option<String>) -> Self {
+        self.filter = command;
+        self
+    }
+    /// 输出前对 JSON 中的字符串和表单字段做百分号解码，不影响 -o 保存的内容
+    pub fn with_decode(mut self, decode: bool) -> Self {
+        self.decode = decode;
+        self
+    }
+    /// 文本 body 边接收边输出，不缓冲也不高亮
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+    /// 原样写出 body 字节，不输出 `[body]` 标题，也不高亮或格式化
+    pub fn with_raw_output(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+    /// 只输出 JSON body 中指定路径的值，如 `data.items.0.name`
+    pub fn with_select(mut self, path: Option<String>) -> Self {
+        self.select = path;
+        self
+    }
+    /// 将 Set-Cookie 的属性拆成多行输出
+    pub fn with_expanded_cookies(mut self, expand: bool) -> Self {
+        self.expand_cookies = expand;
+        self
+    }
+    /// 按名称排序并对齐响应头
+    pub fn with_sorted_headers(mut self, sort: bool) -> Self {
+        self.sort_headers = sort;
+        self
+    }
+    /// 读取 body 时的字节上限，超出后报错，`None` 表示不限制
+    pub fn with_max_body_size(mut self, limit: Option<u64>) -> Self {
+        self.max_body_size = limit;
+        self
+    }
+    /// 在 body 之后输出 `[meta]`，对比 Content-Length 与实际收到的字节数
+    pub fn with_meta(mut self, meta: bool) -> Self {
+        self.meta = meta;
+        self
+    }
+    pub(crate) fn from_opts(opts: &Opts) -> Self {
+        Self::new(!opts.no_pretty, &opts.theme)
+            .with_meta(opts.meta || opts.verbose > 1)
+            .with_max_body_size(body_limit(opts))
+            .with_sorted_headers(opts.sort_headers)
+            .with_expanded_cookies(opts.expand_cookies)
+            .with_select(opts.select.clone())
+            .with_raw_output(opts.raw_output)
+            .with_stream(opts.stream)
+            .with_decode(opts.decode)
+            .with_filter(opts.filter.clone())
+            .with_body_highlight(!opts.no_body_highlight)
+            .with_pager(pager_enabled(opts, io::stdout().is_terminal()))
+    }
+    /// 只输出 status 和 headers，用于 HEAD 请求或 body 写入文件时
+    pub fn print_head(&self, res: &Response, sections: Sections) {
+        if sections.status {
+            print_status(res);
+        }
+        if sections.headers {
+            print_headers(res.headers(), self.sort_headers, self.expand_cookies);
+        }
+    }
+    /// 依次输出 status、headers 和 body，颜色由 colored 的全局设置决定
+    pub async fn print_response(&self, res: Response, sections: Sections) -> Result<()> {
+        self.print_head(&res, sections);
+        if !sections.body {
+            return Ok(());
+        }
+        // 304 没有 body，用一句提示代替空的 [body]
+        if res.status() == StatusCode::NOT_MODIFIED {
+            let note = "Not Modified, the cached copy is still valid";
+            println!("{}", note.truecolor(157, 173, 212));
+            return Ok(());
+        }
+        let encoding = content_encoding(res.headers());
+        let mime = parse_mime(res.headers());
+        let declared = res
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        if mime.as_ref().map(Mime::essence_str) == Some("text/event-stream") {
+            if sections.status || sections.headers {
+                println!("{}", "[events]".bold().truecolor(164, 111, 164));
+            }
+            return print_events(res).await;
+        }
+        // 服务器仍然压缩了或者是二进制内容时，退回到缓冲输出
+        if self.stream && encoding.is_none() && !mime.as_ref().is_some_and(is_binary) {
+            if sections.status || sections.headers {
+                println!("{}", "[body]".bold().truecolor(164, 111, 164));
+            }
+            let received = print_stream(res, mime.as_ref().map_or(UTF_8, text_encoding)).await?;
+            if self.meta {
+                print_meta(declared, received);
+            }
+            return Ok(());
+        }
+        let raw = read_partial_body(res, self.max_body_size).await?;
+        let truncated = truncation_warning(declared, raw.len());
+        let decoded = match decode_content(&raw, encoding.as_deref()) {
+            // 截断的压缩数据无法解压，直接提示截断
+            Err(_) if truncated.is_some() => None,
+            decoded => decoded?,
+        };
+        if self.raw {
+            // 仍然解开 Content-Encoding，否则默认请求的 gzip 会原样写到终端
+            let mut stdout = io::stdout();
+            stdout.write_all(decoded.as_deref().unwrap_or(&raw))?;
+            stdout.flush()?;
+            if let Some(warning) = truncated {
+                eprintln!("{}", warning.yellow().bold());
+            }
+            return Ok(());
+        }
+        if let (Some(ref body), Some(ref encoding), true) = (&decoded, &encoding, sections.headers)
+        {
+            let note = format!("{} → decoded {} bytes", encoding, body.len());
+            println!("{}", note.truecolor(157, 173, 212));
+        }
+        // 只输出 body 时省略 [body] 标题，方便通过管道交给其他工具
+        if sections.status || sections.headers {
+            println!("{}", "[body]".bold().truecolor(164, 111, 164));
+        }
+        let body = decoded.as_deref().unwrap_or(&raw);
+        // 分页程序退出后再输出截断提示和 [meta]
+        match self.pager.then(Pager::start).flatten() {
+            Some(mut pager) => {
+                let printed = self.print_buffered(&mut pager, mime, body);
+                pager.finish().await;
+                printed?;
+            }
+            None => self.print_buffered(&mut io::stdout(), mime, body)?,
+        }
+        if let Some(warning) = truncated {
+            println!("{}", warning.yellow().bold());
+        }
+        if self.meta {
+            print_meta(declared, raw.len());
+        }
+        Ok(())
+    }
+    fn print_buffered(&self, out: &mut impl Write, mime: Option<Mime>, body: &[u8]) -> Result<()> {
+        match mime {
+            _ if self.filter.is_some() => {
+                let output = run_filter(self.filter.as_deref().unwrap_or_default(), body)?;
+                let output = String::from_utf8_lossy(&output);
+                let looks_json = serde_json::from_str::<Value>(&output).is_ok();
+                self.print_body(
+                    out,
+                    looks_json.then_some(mime::APPLICATION_JSON),
+                    output.trim_end(),
+                )?;
+            }
+            _ if self.select.is_some() => self.print_selected(out, mime.as_ref(), body)?,
+            Some(ref m) if is_binary(m) => print_binary_body(out, body.len(), m.essence_str())?,
+            Some(ref m) => self.print_body(out, mime.clone(), &decode_text(body, m))?,
+            // 没有 Content-Type 时根据内容是否为合法 UTF-8 判断
+            None => match std::str::from_utf8(body) {
+                Ok(text) => self.print_body(out, None, text)?,
+                Err(_) => print_binary_body(out, body.len(), "unknown type")?,
+            },
+        }
+        Ok(())
+    }
+    fn print_selected(&self, out: &mut impl Write, m: Option<&Mime>, body: &[u8]) -> Result<()> {
+        let path = self.select.as_deref().unwrap_or_default();
+        let text = match m {
+            Some(m) => decode_text(body, m),
+            None => String::from_utf8_lossy(body).into_owned(),
+        };
+        let mut value: Value = serde_json::from_str(&text)
+            .map_err(|_| anyhow!("--select requires a JSON response body"))?;
+        if self.decode {
+            percent_decode_json(&mut value);
+        }
+        match select_path(&value, path) {
+            Some(selected) if self.pretty => {
+                self.print_syntect(out, &serde_json::to_string_pretty(selected)?, "json")?
+            }
+            Some(selected) => self.print_syntect(out, &selected.to_string(), "json")?,
+            None => eprintln!("{}", format!("No match for --select {}", path).yellow()),
+        }
+        Ok(())
+    }
+    /// 按扩展名高亮本地文件，JSON 会先格式化，不发送任何请求
+    pub fn print_file(&self, path: &Path) -> Result<()> {
+        let ext = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => "json",
+            Some("xml") => "xml",
+            Some("yaml" | "yml") => "yaml",
+            _ => {
+                return Err(anyhow!(
+                    "Cannot tell the syntax of {}, expected a .json, .xml or .yaml file",
+                    path.display()
+                ))
+            }
+        };
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        match prettify_json(&content) {
+            Some(pretty) if ext == "json" && self.pretty => {
+                self.print_syntect(&mut io::stdout(), &pretty, ext)?
+            }
+            _ => self.print_syntect(&mut io::stdout(), content.trim_end(), ext)?,
+        }
+        Ok(())
+    }
+    // 大 body 的高亮很慢，--no-body-highlight 时完全跳过 syntect
+    fn uses_syntect(&self, color: bool) -> bool {
+        color && self.body_highlight
+    }
+    fn print_syntect(&self, out: &mut impl Write, content: &str, ext: &str) -> io::Result<()> {
+        let color = colored::control::SHOULD_COLORIZE.should_colorize();
+        if self.uses_syntect(color) {
+            write!(out, "{}", highlight(content, ext, &self.theme, color))
+        } else {
+            writeln!(out, "{}", content)
+        }
+    }
+    // 写入 out 而不是直接输出到 stdout，便于测试检查输出内容
+    fn print_body(&self, out: &mut impl Write, m: Option<Mime>, body: &str) -> io::Result<()> {
+        let decoded;
+        let body = if self.decode {
+            decoded = decode_body(m.as_ref(), body);
+            &decoded
+        } else {
+            body
+        };
+        match m.as_ref().and_then(syntax_extension) {
+            Some("json") => match prettify_json(body) {
+                Some(pretty) if self.pretty => self.print_syntect(out, &pretty, "json"),
+                _ => self.print_syntect(out, body, "json"),
+            },
+            Some(ext) => self.print_syntect(out, body, ext),
+            None => writeln!(out, "{}", body),
+        }
+    }
+}
+
+// 通过 shell 执行，命令里可以使用引号和管道；另开线程写入 stdin，避免输出较多时互相阻塞
+fn run_filter(command: &str, body: &[u8]) -> Result<Vec<u8>> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = process::Command::new(shell)
+        .args([flag, command])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run --filter {}", command))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let body = body.to_vec();
+    // 命令不读 stdin 就退出时写入会失败，这里忽略，以退出状态为准
+    let writer = std::thread::spawn(move || stdin.write_all(&body));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run --filter {}", command))?;
+    let _ = writer.join();
+    // sh 找不到命令时以 127 退出
+    match output.status.code() {
+        Some(127) if !cfg!(windows) => Err(anyhow!("--filter {}: command not found", command)),
+        _ if !output.status.success() => Err(anyhow!(
+            "--filter {} failed with {}",
+            command,
+            output.status
+        )),
+        _ => Ok(output.stdout),
+    }
+}
+
+// 无法按 UTF-8 解码的字符串保持原样
+fn percent_decode_json(value: &mut Value) {
+    match value {
+        Value::String(s) if s.contains('%') => {
+            if let Ok(decoded) = percent_decode_str(s).decode_utf8() {
+                *s = decoded.into_owned();
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(percent_decode_json),
+        Value::Object(map) => map.values_mut().for_each(percent_decode_json),
+        _ => {}
+    }
+}
+
+// 表单 body 解码后每个字段一行，JSON 解析失败时原样输出
+fn decode_body(m: Option<&Mime>, body: &str) -> String {
+    match m {
+        Some(m) if syntax_extension(m) == Some("json") => {
+            match serde_json::from_str::<Value>(body) {
+                Ok(mut value) => {
+                    percent_decode_json(&mut value);
+                    value.to_string()
+                }
+                Err(_) => body.into(),
+            }
+        }
+        Some(m) if m.essence_str() == "application/x-www-form-urlencoded" => {
+            form_urlencoded::parse(body.trim().as_bytes())
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        _ => body.into(),
+    }
+}
+
+// --porcelain 输出，格式固定，供脚本解析：
+// 第一行为 `状态码\tContent-Type\t字节数`，没有 Content-Type 时为 `-`，
+// 之后紧跟解开 Content-Encoding 后的 body 原始字节，字节数即其长度，没有颜色和标题
+pub(crate) async fn print_porcelain(res: Response, limit: Option<u64>) -> Result<()> {
+    let status = res.status().as_u16();
+    let content_type = res.headers().get(header::CONTENT_TYPE).map_or_else(
+        || "-".into(),
+        |v| String::from_utf8_lossy(v.as_bytes()).into_owned(),
+    );
+    let encoding = content_encoding(res.headers());
+    let raw = read_body_limited(res, limit).await?;
+    let decoded = decode_content(&raw, encoding.as_deref())?;
+    let body = decoded.as_deref().unwrap_or(&raw);
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}\t{}\t{}", status, content_type, body.len())?;
+    stdout.write_all(body)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// --json 输出：同名响应头合并为数组，JSON 响应的 body 直接内嵌
+pub(crate) async fn response_json(res: Response, limit: Option<u64>) -> Result<Value> {
+    let mut headers = Map::new();
+    for (name, value) in res.headers() {
+        let value = Value::String(String::from_utf8_lossy(value.as_bytes()).into_owned());
+        match headers.get_mut(name.as_str()) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(first) => *first = Value::Array(vec![first.take(), value]),
+            None => {
+                headers.insert(name.to_string(), value);
+            }
+        }
+    }
+    let status = res.status().as_u16();
+    let version = format!("{:?}", res.version());
+    let is_json = parse_mime(res.headers())
+        .as_ref()
+        .and_then(syntax_extension)
+        == Some("json");
+    let encoding = content_encoding(res.headers());
+    let raw = read_body_limited(res, limit).await?;
+    let decoded = decode_content(&raw, encoding.as_deref())?;
+    let bytes = decoded.as_deref().unwrap_or(&raw);
+    let body = match serde_json::from_slice(bytes) {
+        Ok(value) if is_json => value,
+        _ => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+    };
+    Ok(serde_json::json!({
+        "status": status,
+        "version": version,
+        "headers": headers,
+        "body": body,
+    }))
+}
+
+pub(crate) fn body_limit(opts: &Opts) -> Option<u64> {
+    Some(opts.max_body_size).filter(|&limit| limit > 0)
+}
+
+// 流式读取 body，超过上限立即中止，避免把超大响应整体读入内存
+pub(crate) async fn read_body_limited(res: Response, limit: Option<u64>) -> Result<Vec<u8>> {
+    collect_body(res, limit, false).await
+}
+
+// 收到的字节少于 Content-Length 时连接中断不算错误，保留已收到的部分，由调用方提示截断
+async fn read_partial_body(res: Response, limit: Option<u64>) -> Result<Vec<u8>> {
+    collect_body(res, limit, true).await
+}
+
+async fn collect_body(res: Response, limit: Option<u64>, allow_truncated: bool) -> Result<Vec<u8>> {
+    let declared = res.content_length().filter(|_| allow_truncated);
+    let mut body = Vec::new();
+    let mut stream = res.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(chunk) => body.extend_from_slice(&chunk),
+            Err(_) if declared.is_some_and(|len| (body.len() as u64) < len) => break,
+            Err(e) => return Err(e.into()),
+        }
+        if let Some(limit) = limit.filter(|&limit| body.len() as u64 > limit) {
+            return Err(anyhow!(
+                "Response body exceeds --max-body-size {} ({} bytes read so far), use -o to save it to a file",
+                format_size(limit as usize),
+                body.len()
+            ));
+        }
+    }
+    Ok(body)
+}
+
+fn truncation_warning(declared: Option<u64>, received: usize) -> Option<String> {
+    declared.filter(|&len| (received as u64) < len).map(|len| {
+        format!(
+            "warning: body truncated, received {} of {} bytes declared by Content-Length",
+            received, len
+        )
+    })
+}
+
+fn content_encoding(headers: &header::HeaderMap) -> Option<String> {
+    headers
+        .get(header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase())
+}
+
+// 输出到终端时关闭了 reqwest 的自动解压，以便保留原始 Content-Encoding；
+// 返回解压后的内容，未压缩或编码不支持时返回 None
+fn decode_content(body: &[u8], encoding: Option<&str>) -> Result<Option<Vec<u8>>> {
+    let mut decoded = Vec::new();
+    let result = match encoding {
+        Some("gzip") | Some("x-gzip") => GzDecoder::new(body).read_to_end(&mut decoded),
+        Some("deflate") => ZlibDecoder::new(body).read_to_end(&mut decoded),
+        Some("br") => brotli::Decompressor::new(body, 4096).read_to_end(&mut decoded),
+        _ => return Ok(None),
+    };
+    result.with_context(|| format!("Failed to decode {} response body", encoding.unwrap()))?;
+    Ok(Some(decoded))
+}
+
+// 非 UTF-8 或无法解析的 Content-Type 视为没有，body 按纯文本输出
+fn parse_mime(headers: &header::HeaderMap) -> Option<Mime> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+fn progress_bar(total: Option<u64>, quiet: bool) -> ProgressBar {
+    if quiet || !io::stdout().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    match total {
+        Some(len) => ProgressBar::new(len).with_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+                .unwrap(),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("{spinner} {bytes} ({bytes_per_sec})").unwrap(),
+        ),
+    }
+}
+
+// 按原始字节流式写入文件，避免二进制内容经过 text() 解码，也不必整体缓存在内存中
+// 写入未完成（出错或被 Ctrl-C 中断）时删除不完整的文件
+struct PartialFile<'a> {
+    path: &'a Path,
+    complete: bool,
+}
+
+impl Drop for PartialFile<'_> {
+    fn drop(&mut self) {
+        if !self.complete {
+            let _ = fs::remove_file(self.path);
+        }
+    }
+}
+
+pub(crate) async fn save_response(res: Response, path: &Path, quiet: bool) -> Result<()> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut partial = PartialFile {
+        path,
+        complete: false,
+    };
+    let bar = progress_bar(res.content_length(), quiet);
+    let mut stream = res.bytes_stream();
+    let mut total = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        total += chunk.len();
+        bar.inc(chunk.len() as u64);
+    }
+    file.flush().await?;
+    partial.complete = true;
+    bar.finish_and_clear();
+    println!("Saved {} bytes to {}", total, path.display());
+    Ok(())
+}
+
+fn content_disposition_filename(value: &str) -> Option<String> {
+    value.split(';').find_map(|part| {
+        let (name, value) = part.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("filename") {
+            return None;
+        }
+        // 只保留最后一段，防止 ../ 之类的路径穿越
+        let value = value.trim().trim_matches('"').rsplit(['/', '\\']).next()?;
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+fn download_filename(headers: &header::HeaderMap, url: &Url) -> String {
+    headers
+        .get(header::CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(content_disposition_filename)
+        .or_else(|| {
+            let segment = url.path_segments()?.next_back()?;
+            (!segment.is_empty()).then(|| segment.to_string())
+        })
+        .unwrap_or_else(|| "index.html".to_string())
+}
+
+// 文件已存在时追加数字后缀：name.ext -> name-1.ext
+fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path.extension().map(|s| s.to_string_lossy().into_owned());
+    (1..)
+        .map(|i| match ext {
+            Some(ref ext) => path.with_file_name(format!("{}-{}.{}", stem, i, ext)),
+            None => path.with_file_name(format!("{}-{}", stem, i)),
+        })
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+pub(crate) async fn download_response(
+    res: Response,
+    output: Option<&Path>,
+    quiet: bool,
+) -> Result<()> {
+    let path = match output {
+        Some(path) => path.to_path_buf(),
+        None => unique_path(PathBuf::from(download_filename(res.headers(), res.url()))),
+    };
+    save_response(res, &path, quiet).await
+}
+
+// reqwest 0.11 不能包装连接器，连接和 TLS 阶段无法在实际请求的连接上测量，只输出 n/a，
+// 不另开连接去估算；没有经过解析器时 dns 同样为 n/a
+fn format_phases(
+    dns: Option<Duration>,
+    headers: Duration,
+    total: Duration,
+) -> Vec<(&'static str, String)> {
+    let ms = |d: Duration| format!("{:.1} ms", d.as_secs_f64() * 1000.0);
+    vec![
+        ("dns:", dns.map_or_else(|| "n/a".to_string(), ms)),
+        ("connect:", "n/a".to_string()),
+        ("tls:", "n/a".to_string()),
+        ("ttfb:", ms(headers)),
+        ("total:", ms(total)),
+    ]
+}
+
+pub(crate) fn print_phases(headers: Duration, total: Duration) {
+    println!("{}", "[timing]".bold().truecolor(164, 111, 164));
+    let dns = *DNS_ELAPSED.lock().unwrap();
+    for (name, value) in format_phases(dns, headers, total) {
+        println!("{:<9}{}", name.truecolor(157, 173, 212), value);
+    }
+}
+
+// 解压并解码已缓冲的 body，JSON 先格式化，避免空白差异干扰对比
+pub(crate) async fn diff_view(res: Response, headers: bool) -> Result<String> {
+    let mut view = String::new();
+    if headers {
+        view.push_str(&format!("{:?} {}\n", res.version(), res.status()));
+        for (name, value) in header_lines(res.headers(), true, false) {
+            view.push_str(&format!("{} {}\n", name, value));
+        }
+    }
+    let encoding = content_encoding(res.headers());
+    let mime = parse_mime(res.headers());
+    let raw = res.bytes().await?;
+    let decoded = decode_content(&raw, encoding.as_deref())?;
+    let body = decoded.as_deref().unwrap_or(&raw);
+    let text = match mime {
+        Some(ref m) => decode_text(body, m),
+        None => String::from_utf8_lossy(body).into_owned(),
+    };
+    let text = prettify_json(&text).unwrap_or(text);
+    view.push_str(&text);
+    if !view.ends_with('\n') {
+        view.push('\n');
+    }
+    Ok(view)
+}
+
+pub(crate) fn unified_diff(old: &str, new: &str, left: &str, right: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(left, right)
+        .to_string()
+}
+
+// 删除的行用红色，新增的行用绿色，与状态码的配色一致
+pub(crate) fn print_diff(diff: &str) {
+    for line in diff.lines() {
+        match line {
+            l if l.starts_with("---") || l.starts_with("+++") => println!("{}", l.bold()),
+            l if l.starts_with("@@") => println!("{}", l.truecolor(117, 157, 255)),
+            l if l.starts_with('-') => println!("{}", l.truecolor(226, 96, 96)),
+            l if l.starts_with('+') => println!("{}", l.truecolor(98, 192, 120)),
+            l => println!("{}", l),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Opts;
+    use crate::common::{serve_once, temp_path};
+    use crate::request::build_client;
+    use clap::Parser;
+    use reqwest::header::HeaderValue;
+    use reqwest::Client;
+
+    #[test]
+    fn print_body_handles_empty_body() {
+        let opts = Opts::try_parse_from(["httpie", "options", "https://a.b"]).unwrap();
+        let printer = Printer::from_opts(&opts);
+        // 高亮空内容时不输出任何字符，不高亮时只有换行
+        let expected: &[u8] = if colored::control::SHOULD_COLORIZE.should_colorize() {
+            b""
+        } else {
+            b"\n"
+        };
+        for m in [mime::APPLICATION_JSON, mime::TEXT_HTML] {
+            let mut out = Vec::new();
+            printer.print_body(&mut out, Some(m), "").unwrap();
+            assert_eq!(out, expected);
+        }
+        let mut out = Vec::new();
+        printer.print_body(&mut out, None, "").unwrap();
+        assert_eq!(out, b"\n");
+    }
+    #[test]
+    fn download_filename_works() {
+        let url = Url::parse("https://a.b/files/report.pdf?x=1").unwrap();
+        let mut headers = header::HeaderMap::new();
+        assert_eq!(download_filename(&headers, &url), "report.pdf");
+        assert_eq!(
+            download_filename(&headers, &Url::parse("https://a.b/").unwrap()),
+            "index.html"
+        );
+        assert_eq!(
+            download_filename(&headers, &Url::parse("https://a.b").unwrap()),
+            "index.html"
+        );
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"data.csv\"".parse().unwrap(),
+        );
+        assert_eq!(download_filename(&headers, &url), "data.csv");
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=../../etc/passwd".parse().unwrap(),
+        );
+        assert_eq!(download_filename(&headers, &url), "passwd");
+        headers.insert(header::CONTENT_DISPOSITION, "inline".parse().unwrap());
+        assert_eq!(download_filename(&headers, &url), "report.pdf");
+    }
+    #[test]
+    fn unique_path_appends_suffix() {
+        let dir = temp_path("unique-path-test");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        assert_eq!(unique_path(path.clone()), path);
+        fs::write(&path, "").unwrap();
+        assert_eq!(unique_path(path.clone()), dir.join("file-1.txt"));
+        fs::write(dir.join("file-1.txt"), "").unwrap();
+        assert_eq!(unique_path(path), dir.join("file-2.txt"));
+        fs::write(dir.join("noext"), "").unwrap();
+        assert_eq!(unique_path(dir.join("noext")), dir.join("noext-1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn progress_bar_is_hidden_when_quiet() {
+        assert!(progress_bar(Some(10), true).is_hidden());
+        assert!(progress_bar(None, true).is_hidden());
+    }
+    #[test]
+    fn prettify_json_works() {
+        assert_eq!(
+            prettify_json("{\"b\":1,\"a\":[1,2]}").unwrap(),
+            "{\n  \"b\": 1,\n  \"a\": [\n    1,\n    2\n  ]\n}"
+        );
+        assert_eq!(prettify_json("{\"a\":"), None);
+        assert_eq!(prettify_json("<html>"), None);
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        assert!(Printer::from_opts(&opts).pretty);
+        let opts = Opts::try_parse_from(["httpie", "--no-pretty", "get", "https://a.b"]).unwrap();
+        assert!(!Printer::from_opts(&opts).pretty);
+        let opts =
+            Opts::try_parse_from(["httpie", "--no-pretty", "--pretty", "get", "https://a.b"])
+                .unwrap();
+        assert!(Printer::from_opts(&opts).pretty);
+    }
+    #[test]
+    fn theme_is_validated() {
+        assert_eq!(parse_theme("InspiredGitHub").unwrap(), "InspiredGitHub");
+        assert_eq!(
+            parse_theme("Solarized (light)").unwrap(),
+            "Solarized (light)"
+        );
+        let err = parse_theme("nope").unwrap_err().to_string();
+        assert!(err.contains("Unknown theme nope"));
+        assert!(err.contains(DEFAULT_THEME));
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        assert_eq!(opts.theme, DEFAULT_THEME);
+        assert!(Opts::try_parse_from(["httpie", "--theme", "nope", "get", "https://a.b"]).is_err());
+        assert!(
+            Opts::try_parse_from(["httpie", "--list-themes"])
+                .unwrap()
+                .list_themes
+        );
+    }
+    #[test]
+    fn syntax_extension_works() {
+        let ext = |s: &str| syntax_extension(&s.parse().unwrap());
+        assert_eq!(ext("application/json"), Some("json"));
+        assert_eq!(ext("application/json; charset=utf-8"), Some("json"));
+        assert_eq!(ext("text/html; charset=utf-8"), Some("html"));
+        assert_eq!(ext("text/css"), Some("css"));
+        assert_eq!(ext("application/javascript"), Some("js"));
+        assert_eq!(ext("application/xml"), Some("xml"));
+        assert_eq!(ext("text/xml; charset=utf-8"), Some("xml"));
+        assert_eq!(ext("application/x-yaml"), Some("yaml"));
+        assert_eq!(ext("text/yaml"), Some("yaml"));
+        assert_eq!(ext("text/plain"), None);
+        let ps = SyntaxSet::load_defaults_newlines();
+        for e in ["json", "html", "css", "js", "xml", "yaml"] {
+            assert!(ps.find_syntax_by_extension(e).is_some(), "{}", e);
+        }
+    }
+    #[test]
+    fn parse_mime_handles_bad_content_type() {
+        let mut headers = header::HeaderMap::new();
+        assert_eq!(parse_mime(&headers), None);
+        headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+        assert_eq!(parse_mime(&headers), Some(mime::APPLICATION_JSON));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_bytes(b"text/\xff\xfe").unwrap(),
+        );
+        assert_eq!(parse_mime(&headers), None);
+        headers.insert(header::CONTENT_TYPE, "garbage;;==".parse().unwrap());
+        assert_eq!(parse_mime(&headers), None);
+    }
+    #[test]
+    fn is_binary_works() {
+        let binary = |s: &str| is_binary(&s.parse().unwrap());
+        assert!(binary("image/png"));
+        assert!(binary("application/octet-stream"));
+        assert!(binary("application/pdf"));
+        assert!(!binary("text/plain; charset=utf-8"));
+        assert!(!binary("application/json"));
+        assert!(!binary("application/problem+json"));
+        assert!(!binary("application/atom+xml"));
+        assert!(!binary("application/xml"));
+        assert!(!binary("application/javascript"));
+        assert!(!binary("image/svg+xml"));
+    }
+    #[test]
+    fn format_size_works() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KB");
+        assert_eq!(format_size(12698), "12.4 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+    #[test]
+    fn printer_falls_back_to_default_theme() {
+        assert_eq!(Printer::new(true, "no-such-theme").theme, DEFAULT_THEME);
+        assert_eq!(
+            Printer::new(true, "Solarized (dark)").theme,
+            "Solarized (dark)"
+        );
+    }
+    #[tokio::test]
+    async fn response_json_structures_response() {
+        let (url, _server) = serve_once("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\nContent-Length: 8\r\n\r\n{\"a\": 1}",
+        );
+        let value = response_json(Client::new().get(&url).send().await.unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(value["status"], 200);
+        assert_eq!(value["version"], "HTTP/1.1");
+        assert_eq!(value["headers"]["content-type"], "application/json");
+        assert_eq!(
+            value["headers"]["set-cookie"],
+            serde_json::json!(["a=1", "b=2"])
+        );
+        assert_eq!(value["body"], serde_json::json!({"a": 1}));
+
+        let (url, _server) = serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 4\r\n\r\n{..}",
+        );
+        let value = response_json(Client::new().get(&url).send().await.unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(value["status"], 404);
+        assert_eq!(value["body"], "{..}");
+        assert!(Opts::try_parse_from(["httpie", "--json", "-d", "get", "https://a.b"]).is_err());
+    }
+    #[test]
+    fn format_meta_flags_mismatch() {
+        assert_eq!(
+            format_meta(Some(12), 12),
+            ("content-length: 12, received: 12 bytes".into(), false)
+        );
+        assert_eq!(
+            format_meta(Some(12), 8),
+            ("content-length: 12, received: 8 bytes".into(), true)
+        );
+        assert_eq!(
+            format_meta(None, 8),
+            ("content-length: none, received: 8 bytes".into(), false)
+        );
+    }
+    #[test]
+    fn decode_text_uses_charset() {
+        let latin1: Mime = "text/plain; charset=iso-8859-1".parse().unwrap();
+        assert_eq!(decode_text(b"caf\xe9", &latin1), "café");
+        assert_eq!(decode_text("café".as_bytes(), &mime::TEXT_PLAIN), "café");
+    }
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+    #[test]
+    fn decode_content_works() {
+        let json = br#"{"compressed":true}"#;
+        assert_eq!(
+            decode_content(&gzip(json), Some("gzip")).unwrap().unwrap(),
+            json
+        );
+        let mut br = Vec::new();
+        brotli::CompressorWriter::new(&mut br, 4096, 5, 22)
+            .write_all(json)
+            .unwrap();
+        assert_eq!(decode_content(&br, Some("br")).unwrap().unwrap(), json);
+        assert_eq!(decode_content(json, None).unwrap(), None);
+        assert_eq!(decode_content(json, Some("identity")).unwrap(), None);
+        assert!(decode_content(json, Some("gzip")).is_err());
+    }
+    #[tokio::test]
+    async fn gzipped_json_is_readable() {
+        let body = gzip(br#"{"a": 1}"#);
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        response.extend(body);
+        let (url, _server) = serve_once(response);
+        let opts = Opts::try_parse_from(["httpie", "--json", "get", &url]).unwrap();
+        let res = build_client(&opts).unwrap().get(&url).send().await.unwrap();
+        let value = response_json(res, None).await.unwrap();
+        assert_eq!(value["headers"]["content-encoding"], "gzip");
+        assert_eq!(value["body"], serde_json::json!({"a": 1}));
+    }
+    #[tokio::test]
+    async fn downloads_are_saved_decoded() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"deflated body").unwrap();
+        let body = encoder.finish().unwrap();
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: deflate\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        response.extend(body);
+        let (url, _server) = serve_once(response);
+        let path = temp_path("deflate-download.txt");
+        let opts =
+            Opts::try_parse_from(["httpie", "-o", path.to_str().unwrap(), "get", &url]).unwrap();
+        let res = build_client(&opts).unwrap().get(&url).send().await.unwrap();
+        save_response(res, &path, true).await.unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"deflated body");
+        fs::remove_file(path).unwrap();
+    }
+    #[tokio::test]
+    async fn read_body_limited_aborts_on_overflow() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789";
+        let (url, _server) = serve_once(response);
+        let res = Client::new().get(&url).send().await.unwrap();
+        let err = read_body_limited(res, Some(4)).await.unwrap_err();
+        assert!(err.to_string().contains("--max-body-size 4 B"));
+        assert!(err.to_string().contains("10 bytes read"));
+        let (url, _server) = serve_once(response);
+        let res = Client::new().get(&url).send().await.unwrap();
+        assert_eq!(
+            read_body_limited(res, Some(10)).await.unwrap(),
+            b"0123456789"
+        );
+
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        assert_eq!(body_limit(&opts), Some(100 * 1024 * 1024));
+        let opts =
+            Opts::try_parse_from(["httpie", "--max-body-size", "0", "get", "https://a.b"]).unwrap();
+        assert_eq!(body_limit(&opts), None);
+    }
+    #[test]
+    fn header_lines_sorts_and_aligns() {
+        let mut headers = header::HeaderMap::new();
+        headers.insert("x-request-id", "42".parse().unwrap());
+        headers.append(header::SET_COOKIE, "b=2".parse().unwrap());
+        headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        headers.append(header::SET_COOKIE, "a=1".parse().unwrap());
+        headers.insert(header::AGE, "0".parse().unwrap());
+        let lines: Vec<String> = header_lines(&headers, true, false)
+            .into_iter()
+            .map(|(name, value)| format!("{} {}", name, value))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "age:          \"0\"",
+                "content-type: \"text/plain\"",
+                "set-cookie:   \"b=2\"",
+                "set-cookie:   \"a=1\"",
+                "x-request-id: \"42\"",
+            ]
+        );
+        let unsorted = header_lines(&headers, false, false);
+        assert_eq!(unsorted[0], ("x-request-id:".into(), "\"42\"".into()));
+    }
+    #[test]
+    fn expand_set_cookie_works() {
+        assert_eq!(
+            expand_set_cookie(
+                "sid=a=b; Path=/api; Domain=.example.com; Expires=Thu, 01 Jan 2099 00:00:00 GMT; HttpOnly; Secure; SameSite=Lax"
+            ),
+            "sid=a=b\n    Path: /api\n    Domain: .example.com\n    Expires: Thu, 01 Jan 2099 00:00:00 GMT\n    HttpOnly\n    Secure\n    SameSite: Lax"
+        );
+        assert_eq!(expand_set_cookie("theme=dark"), "theme=dark");
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::SET_COOKIE, "a=1; Path=/".parse().unwrap());
+        headers.insert(header::SERVER, "test".parse().unwrap());
+        let lines = header_lines(&headers, false, true);
+        assert_eq!(lines[0].1, "a=1\n    Path: /");
+        assert_eq!(lines[1].1, "\"test\"");
+    }
+    #[test]
+    fn shell_quote_works() {
+        assert_eq!(shell_quote("http://a.com/x"), "http://a.com/x");
+        assert_eq!(shell_quote("http://a.com/x?y=1"), "'http://a.com/x?y=1'");
+        assert_eq!(shell_quote("plain"), "plain");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("{\"a\":1}"), "'{\"a\":1}'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+    #[test]
+    fn curl_command_works() {
+        let client = Client::new();
+        let req = client
+            .post("http://localhost/items?q=a b")
+            .header("X-Note", "it's")
+            .body("{\"name\":\"x y\"}")
+            .build()
+            .unwrap();
+        assert_eq!(
+            curl_command(&req, &header::HeaderMap::new()),
+            "curl -X POST -H 'x-note: it'\\''s' --data-binary '{\"name\":\"x y\"}' 'http://localhost/items?q=a%20b'"
+        );
+        let req = client.head("http://localhost/").build().unwrap();
+        let mut defaults = header::HeaderMap::new();
+        defaults.insert(header::USER_AGENT, "Rust HTTPIE".parse().unwrap());
+        assert_eq!(
+            curl_command(&req, &defaults),
+            "curl -I -H 'user-agent: Rust HTTPIE' http://localhost/"
+        );
+    }
+    #[test]
+    fn select_path_works() {
+        let value: Value = serde_json::from_str(
+            r#"{"data":{"items":[{"name":"a"},{"name":"b","tags":["x","y"]}],"a.b":1}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            select_path(&value, "data.items.1.name"),
+            Some(&Value::from("b"))
+        );
+        assert_eq!(
+            select_path(&value, "data.items.1.tags.0"),
+            Some(&Value::from("x"))
+        );
+        assert_eq!(
+            select_path(&value, "data.items.0"),
+            value["data"]["items"].get(0)
+        );
+        assert_eq!(select_path(&value, ""), Some(&value));
+        assert_eq!(select_path(&value, "data.items.2"), None);
+        assert_eq!(select_path(&value, "data.items.name"), None);
+        assert_eq!(select_path(&value, "data.missing"), None);
+        assert_eq!(select_path(&value, "data.items.0.name.x"), None);
+    }
+    #[test]
+    fn status_color_follows_class() {
+        let color = |code| status_color(StatusCode::from_u16(code).unwrap());
+        assert_eq!(color(100), (117, 157, 255));
+        assert_eq!(color(200), (98, 192, 120));
+        assert_eq!(color(204), (98, 192, 120));
+        assert_eq!(color(301), (222, 184, 84));
+        assert_eq!(color(404), (226, 96, 96));
+        assert_eq!(color(503), (226, 96, 96));
+        assert_eq!(color(999), (117, 157, 255));
+    }
+    #[test]
+    fn format_latencies_works() {
+        let ms = Duration::from_millis;
+        assert_eq!(
+            format_latencies(&[ms(12), ms(30), ms(18)]),
+            "3 requests, min 12 ms, max 30 ms, mean 20 ms"
+        );
+        assert_eq!(format_latencies(&[]), "0 requests");
+    }
+    #[test]
+    fn partial_file_is_removed_unless_complete() {
+        let path = temp_path("partial.bin");
+        fs::write(&path, b"part").unwrap();
+        drop(PartialFile {
+            path: &path,
+            complete: false,
+        });
+        assert!(!path.exists());
+        fs::write(&path, b"done").unwrap();
+        drop(PartialFile {
+            path: &path,
+            complete: true,
+        });
+        assert_eq!(fs::read(&path).unwrap(), b"done");
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn sse_parser_handles_framing() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.feed(": keep-alive\nevent: upd"), vec![]);
+        assert_eq!(
+            parser.feed("ate\r\nid: 7\ndata: a\ndata:b\n\ndata: {\"x\": 1}\n"),
+            vec![SseEvent {
+                event: Some("update".into()),
+                data: "a\nb".into(),
+                id: Some("7".into()),
+            }]
+        );
+        assert_eq!(
+            parser.feed("\nevent: empty\n\n"),
+            vec![SseEvent {
+                event: None,
+                data: "{\"x\": 1}".into(),
+                id: None,
+            }]
+        );
+        // 上面没有 data 的 empty 事件被丢弃；只写字段名 data 时是一个 data 为空的事件
+        assert_eq!(
+            parser.feed("data\n\n"),
+            vec![SseEvent {
+                event: None,
+                data: "".into(),
+                id: None,
+            }]
+        );
+    }
+    #[test]
+    fn percent_decode_json_walks_nested_values() {
+        let mut value = serde_json::json!({
+            "next": "https%3A%2F%2Fa.b%2F%3Fq%3D1",
+            "items": [{"name": "caf%C3%A9"}, 5, ["a%20b", "bad%FF"]],
+            "plain": "100%",
+        });
+        percent_decode_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "next": "https://a.b/?q=1",
+                "items": [{"name": "café"}, 5, ["a b", "bad%FF"]],
+                "plain": "100%",
+            })
+        );
+        let form = "application/x-www-form-urlencoded".parse().unwrap();
+        assert_eq!(decode_body(Some(&form), "a=x%2By&b=c+d\n"), "a=x+y\nb=c d");
+        let json = mime::APPLICATION_JSON;
+        assert_eq!(decode_body(Some(&json), "not json%20"), "not json%20");
+    }
+    #[cfg(unix)]
+    #[test]
+    fn run_filter_pipes_body_through_command() {
+        assert_eq!(
+            run_filter("tr a-z A-Z", b"{\"a\": 1}").unwrap(),
+            b"{\"A\": 1}"
+        );
+        let err = run_filter("exit 3", b"").unwrap_err();
+        assert!(err.to_string().contains("exit status: 3"));
+        let err = run_filter("httpie-rust-missing-filter", b"x").unwrap_err();
+        assert!(err.to_string().contains("command not found"));
+    }
+    #[test]
+    fn timings_mark_unmeasured_phases() {
+        assert_eq!(
+            format_phases(
+                Some(Duration::from_micros(2500)),
+                Duration::from_millis(120),
+                Duration::from_millis(312)
+            ),
+            [
+                ("dns:", "2.5 ms".to_string()),
+                ("connect:", "n/a".to_string()),
+                ("tls:", "n/a".to_string()),
+                ("ttfb:", "120.0 ms".to_string()),
+                ("total:", "312.0 ms".to_string()),
+            ]
+        );
+        let phases = format_phases(None, Duration::ZERO, Duration::ZERO);
+        assert_eq!(phases[0], ("dns:", "n/a".to_string()));
+    }
+    #[test]
+    fn truncation_warning_needs_a_short_body() {
+        assert_eq!(
+            truncation_warning(Some(10), 3).unwrap(),
+            "warning: body truncated, received 3 of 10 bytes declared by Content-Length"
+        );
+        assert!(truncation_warning(Some(3), 3).is_none());
+        assert!(truncation_warning(None, 3).is_none());
+    }
+    #[tokio::test]
+    async fn diff_pretty_prints_json_before_comparing() {
+        let response = |body: &'static str| -> Response {
+            http::Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .unwrap()
+                .into()
+        };
+        let old = diff_view(response(r#"{"name":"a","tags":[1,2],"ok":true}"#), false)
+            .await
+            .unwrap();
+        let new = diff_view(
+            response(r#"{ "name": "b", "tags": [1, 2], "ok": true }"#),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            unified_diff(&old, &new, "staging", "production"),
+            "--- staging\n+++ production\n@@ -1,5 +1,5 @@\n {\n-  \"name\": \"a\",\n+  \"name\": \"b\",\n   \"tags\": [\n     1,\n     2\n"
+        );
+        let same = diff_view(
+            response(r#"{"name": "a", "tags": [1, 2], "ok": true}"#),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(old, same);
+    }
+    #[test]
+    fn no_body_highlight_skips_syntect() {
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        let printer = Printer::from_opts(&opts);
+        assert!(printer.uses_syntect(true));
+        assert!(!printer.uses_syntect(false));
+        let opts =
+            Opts::try_parse_from(["httpie", "--no-body-highlight", "get", "https://a.b"]).unwrap();
+        let printer = Printer::from_opts(&opts);
+        assert!(!printer.uses_syntect(true));
+        let mut out = Vec::new();
+        printer
+            .print_body(&mut out, Some(mime::APPLICATION_JSON), r#"{"a":1}"#)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\n  \"a\": 1\n}\n");
+    }
+}
@@ -0,0 +1,2589 @@
+//! 构造和发送请求：请求体、认证、代理、超时、重试和重定向
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use colored::Colorize;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::future::try_join;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::HeaderValue;
+use reqwest::multipart::{Form, Part};
+use reqwest::tls::TlsInfo;
+use reqwest::{
+    header, redirect, Body, Client, Identity, Method, Proxy, Request, RequestBuilder, Response,
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::io::ReaderStream;
+use tracing::{debug, info, warn};
+use url::Url;
+
+#[cfg(feature = "clipboard")]
+use crate::cli::read_clipboard;
+use crate::cli::{
+    expand_env, positive_seconds, read_body, read_body_file, BasicAuth, BodyField, KvPair, Opts,
+    RawBody, RequestItem,
+};
+use crate::history::{append_history, history_path, HistoryEntry, MAX_HISTORY_BYTES};
+use crate::print::{
+    body_limit, curl_command, diff_view, download_response, format_latencies, print_diff,
+    print_phases, print_porcelain, print_request, print_timing, read_body_limited, response_json,
+    save_response, unified_diff, Printer, Sections,
+};
+use crate::session::{domain_matches, session_path, SessionStore};
+use crate::tls::{print_tls, tls_details, TlsDetails};
+
+// 没有 body 时不做处理，保存的请求仍是未压缩的原始 body
+fn compress_body(req: &mut Request) -> Result<()> {
+    let body = match req.body() {
+        Some(body) => body.as_bytes().ok_or_else(|| {
+            anyhow!("--compress cannot be used with a streaming body such as --multipart")
+        })?,
+        None => return Ok(()),
+    };
+    if body.is_empty() {
+        return Ok(());
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    let compressed = encoder.finish()?;
+    eprintln!(
+        "{}",
+        "warning: the request body is gzip-compressed (--compress), not all servers accept it"
+            .yellow()
+    );
+    req.headers_mut()
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    *req.body_mut() = Some(compressed.into());
+    Ok(())
+}
+
+pub(crate) fn split_items(items: &[RequestItem]) -> (header::HeaderMap, Vec<&BodyField>) {
+    let mut headers = header::HeaderMap::new();
+    let mut fields = Vec::new();
+    for item in items.iter() {
+        match item {
+            RequestItem::Header(name, value) => {
+                headers.append(name, value.clone());
+            }
+            RequestItem::Field(field) => fields.push(field),
+        }
+    }
+    (headers, fields)
+}
+
+// JSON 和表单 body 需要 key@path 的文件内容，二进制文件只能作为 multipart 文件上传
+fn read_text_fields(args: &[&BodyField]) -> Result<Vec<BodyField>> {
+    args.iter()
+        .map(|arg| {
+            let path = match arg.file {
+                Some(ref path) => path,
+                None => return Ok((*arg).clone()),
+            };
+            let content = fs::read(path).with_context(|| {
+                format!("Failed to read file {} for field {}", path.display(), arg.key)
+            })?;
+            let text = String::from_utf8(content).map_err(|_| anyhow!(
+                "File {} for field {} is not valid UTF-8 and can only be uploaded as multipart (--multipart or --form)",
+                path.display(),
+                arg.key
+            ))?;
+            Ok(BodyField {
+                key: arg.key.clone(),
+                value: Value::String(text),
+                file: None,
+            })
+        })
+        .collect()
+}
+
+// 普通字段按文本发送，key@path 字段作为文件上传，按扩展名推断 MIME 类型
+fn multipart_form(args: &[&BodyField]) -> Result<Form> {
+    let mut form = Form::new();
+    for arg in args.iter() {
+        form = match arg.file {
+            Some(ref path) => {
+                // 边读边发送，不把文件整个读进内存
+                let file = fs::File::open(path)
+                    .with_context(|| format!("Failed to read file {}", path.display()))?;
+                let length = file.metadata()?.len();
+                let stream = ReaderStream::new(tokio::fs::File::from_std(file));
+                let mut part = Part::stream_with_length(Body::wrap_stream(stream), length)
+                    .mime_str(mime_guess::from_path(path).first_or_octet_stream().as_ref())?;
+                if let Some(name) = path.file_name() {
+                    part = part.file_name(name.to_string_lossy().into_owned());
+                }
+                form.part(arg.key.clone(), part)
+            }
+            None => match arg.value {
+                Value::String(ref s) => form.text(arg.key.clone(), s.clone()),
+                ref v => form.text(arg.key.clone(), v.to_string()),
+            },
+        };
+    }
+    Ok(form)
+}
+
+fn with_fields(req: RequestBuilder, args: &[&BodyField], form: bool) -> RequestBuilder {
+    if form {
+        // 表单里没有类型，JSON 值直接按其文本形式发送
+        let body: Vec<(&String, String)> = args
+            .iter()
+            .map(|arg| match arg.value {
+                Value::String(ref s) => (&arg.key, s.clone()),
+                ref v => (&arg.key, v.to_string()),
+            })
+            .collect();
+        req.form(&body)
+    } else {
+        req.json(&fields_object(args))
+    }
+}
+
+// 同一个 key 出现多次时合并为数组，只出现一次仍保持原值
+fn fields_object(args: &[&BodyField]) -> Map<String, Value> {
+    let mut body = Map::new();
+    let mut repeated = HashSet::new();
+    for arg in args.iter() {
+        match body.get_mut(&arg.key) {
+            Some(Value::Array(values)) if repeated.contains(&arg.key) => {
+                values.push(arg.value.clone())
+            }
+            Some(existing) => {
+                *existing = Value::Array(vec![existing.take(), arg.value.clone()]);
+                repeated.insert(arg.key.clone());
+            }
+            None => {
+                body.insert(arg.key.clone(), arg.value.clone());
+            }
+        }
+    }
+    body
+}
+
+fn load_body_template(path: &Path) -> Result<Value> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read body template {}", path.display()))?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in body template {}", path.display()))?;
+    if !value.is_object() {
+        return Err(anyhow!(
+            "Body template {} must contain a JSON object",
+            path.display()
+        ));
+    }
+    Ok(value)
+}
+
+// 两边都是对象时逐键递归合并，其余情况（包括对象被标量覆盖）直接用 patch 替换
+fn merge_json(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+/// 与命令行无关的请求描述
+#[derive(Debug)]
+pub struct RequestSpec {
+    pub method: Method,
+    pub url: String,
+    pub query: Vec<KvPair>,
+    /// 请求头和 body 字段，有字段时按 JSON（`form` 为真时按表单）发送
+    pub items: Vec<RequestItem>,
+    /// 表单中有 `key@path` 字段时自动改用 multipart
+    pub form: bool,
+    pub multipart: bool,
+    /// HTTP Basic 认证的用户名和密码
+    pub basic_auth: Option<(String, String)>,
+}
+
+impl RequestSpec {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            query: Vec::new(),
+            items: Vec::new(),
+            form: false,
+            multipart: false,
+            basic_auth: None,
+        }
+    }
+}
+
+/// `--save-request` 保存的请求，可以用 `replay` 子命令重新发送。
+///
+/// 只保存请求本身的请求头，User-Agent 等默认请求头在重放时重新添加。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+impl SavedRequest {
+    /// 从构造好的请求生成，流式（multipart）或非 UTF-8 的 body 无法保存
+    pub fn from_request(req: &Request) -> Result<Self> {
+        let headers = req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = value
+                    .to_str()
+                    .with_context(|| format!("Header {} is not valid UTF-8", name))?;
+                Ok((name.to_string(), value.to_string()))
+            })
+            .collect::<Result<_>>()?;
+        let body = match req.body() {
+            Some(body) => {
+                let bytes = body
+                    .as_bytes()
+                    .ok_or_else(|| anyhow!("Cannot save a streaming request body"))?;
+                let text = std::str::from_utf8(bytes)
+                    .map_err(|_| anyhow!("Cannot save a request body that is not valid UTF-8"))?;
+                Some(text.to_string())
+            }
+            None => None,
+        };
+        Ok(Self {
+            method: req.method().to_string(),
+            url: req.url().to_string(),
+            headers,
+            body,
+        })
+    }
+
+    /// 读取 JSON 格式的请求文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read saved request {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid saved request {}", path.display()))
+    }
+
+    /// 以格式化的 JSON 写入文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to save request to {}", path.display()))
+    }
+}
+
+/// 按 `spec` 构造请求并发送，不读取响应 body
+pub async fn execute_request(client: &Client, spec: &RequestSpec) -> Result<Response> {
+    // 与命令行共用 prepare/build，两边构造请求的方式保持一致
+    let http = HttpRequest::from_spec(client.clone(), spec)?;
+    let req = http.build(http.prepare(spec.method.clone(), &spec.url, &spec.items, &None)?)?;
+    Ok(client.execute(req).await?)
+}
+
+// 重试总耗时上限，避免退避时间成倍增长导致长时间挂起
+const MAX_RETRY_ELAPSED: Duration = Duration::from_secs(60);
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+fn retry_backoff(base_ms: u64, attempt: u32) -> Duration {
+    Duration::from_millis(base_ms.saturating_mul(1 << attempt.min(16)))
+}
+
+// 一次请求流程的进度，超过 --max-time 时用于提示；每个请求各用一份，--parallel 时互不影响
+#[derive(Default)]
+struct Progress {
+    // 已发出的次数（含重试）
+    attempts: Cell<u32>,
+    responded: Cell<bool>,
+}
+
+// 收到的响应，以及 body 读完后输出 [timing] 和 [redirected to] 所需的信息
+pub(crate) struct Exchange {
+    res: Response,
+    // 请求开始时间和收到响应头的耗时
+    timing: (Instant, Duration),
+    // 跟随重定向后的最终地址，与请求地址相同时为 None
+    redirected: Option<Url>,
+    // --verbose-tls 且为 HTTPS 时，实际连接上的服务器证书信息
+    tls: Option<Result<TlsDetails>>,
+}
+
+pub(crate) struct HttpRequest {
+    client: Client,
+    default_headers: header::HeaderMap,
+    verbose: i32,
+    // -v 或 --print 中的 H、B：是否输出请求头和请求 body
+    request_sections: (bool, bool),
+    auth: Option<BasicAuth>,
+    query: Vec<(String, String)>,
+    form: bool,
+    multipart: bool,
+    body_template: Option<Value>,
+    // --data-urlencode 的字段，值不经过 key=value 语法解析，追加在其他字段之后
+    data_urlencode: Vec<BodyField>,
+    explicit_content_type: bool,
+    output: Option<PathBuf>,
+    download: bool,
+    quiet: bool,
+    json: bool,
+    porcelain: bool,
+    max_body_size: Option<u64>,
+    sections: Sections,
+    meta: bool,
+    retries: u32,
+    retry_delay: u64,
+    retry_all: bool,
+    offline: bool,
+    curl: bool,
+    fail: bool,
+    body_only_on_error: bool,
+    save_request: Option<PathBuf>,
+    compress: bool,
+    method_override: bool,
+    verbose_tls: bool,
+    host_header: Option<HeaderValue>,
+    // 自行跟随重定向时使用，与 build_client 里的重定向策略一致
+    follow_redirects: bool,
+    max_redirects: Option<usize>,
+    timings: bool,
+    history: Option<PathBuf>,
+    max_time: Option<Duration>,
+    printer: Printer,
+}
+
+impl HttpRequest {
+    // 库调用方没有命令行参数，其余设置取默认值
+    fn from_spec(client: Client, spec: &RequestSpec) -> Result<Self> {
+        let opts = Opts::try_parse_from(["httpie"])?;
+        let mut http = Self::new(client, &opts)?;
+        http.query = spec
+            .query
+            .iter()
+            .map(|kv| (kv.key.clone(), kv.value.clone()))
+            .collect();
+        http.form = spec.form;
+        http.multipart = spec.multipart;
+        http.auth = spec
+            .basic_auth
+            .clone()
+            .map(|(username, password)| BasicAuth {
+                username,
+                password: Some(password),
+            });
+        Ok(http)
+    }
+    pub(crate) fn new(client: Client, opts: &Opts) -> Result<Self> {
+        Ok(Self {
+            client,
+            default_headers: build_headers(opts)?,
+            verbose: opts.verbose,
+            request_sections: match opts.print {
+                Some(spec) => (spec.request_headers, spec.request_body),
+                None => (opts.verbose > 0, opts.verbose > 0),
+            },
+            auth: opts.auth.clone(),
+            query: opts
+                .query
+                .iter()
+                .map(|kv| Ok((kv.key.clone(), expand_env(&kv.value, opts.allow_unset)?)))
+                .collect::<Result<_>>()?,
+            form: opts.form || !opts.data_urlencode.is_empty(),
+            multipart: opts.multipart,
+            data_urlencode: opts
+                .data_urlencode
+                .iter()
+                .map(|kv| BodyField {
+                    key: kv.key.clone(),
+                    value: Value::String(kv.value.clone()),
+                    file: None,
+                })
+                .collect(),
+            body_template: opts
+                .body_template
+                .as_deref()
+                .map(load_body_template)
+                .transpose()?,
+            explicit_content_type: opts
+                .headers
+                .iter()
+                .any(|(name, _)| name == header::CONTENT_TYPE),
+            output: opts.output.clone(),
+            download: opts.download,
+            quiet: opts.quiet,
+            json: opts.json,
+            porcelain: opts.porcelain,
+            max_body_size: body_limit(opts),
+            sections: Sections::from_opts(opts),
+            meta: opts.meta || opts.verbose > 1,
+            retries: opts.retries,
+            retry_delay: opts.retry_delay,
+            retry_all: opts.retry_all,
+            offline: opts.offline,
+            curl: opts.curl,
+            fail: opts.fail,
+            body_only_on_error: opts.body_only_on_error,
+            save_request: opts.save_request.clone(),
+            compress: opts.compress,
+            method_override: opts.method_override,
+            verbose_tls: opts.verbose_tls,
+            host_header: opts.host_header.clone(),
+            follow_redirects: !opts.no_follow,
+            max_redirects: opts.max_redirects,
+            timings: opts.timings,
+            max_time: opts.max_time,
+            history: match opts.history {
+                true => Some(history_path()?),
+                false => None,
+            },
+            printer: Printer::from_opts(opts),
+        })
+    }
+    // reqwest 跟随重定向时会把显式的 Host 原样带到新的主机，所以 --host-header 时
+    // 由这里跟随，跨主机后去掉 Host 和认证相关的头，其余规则与 reqwest 一致
+    async fn dispatch(&self, req: Request) -> Result<Response> {
+        if self.host_header.is_none() {
+            return Ok(self.client.execute(req).await?);
+        }
+        let origin = |url: &Url| {
+            (
+                url.host_str().map(str::to_string),
+                url.port_or_known_default(),
+            )
+        };
+        let requested = origin(req.url());
+        let mut req = req;
+        let mut hops = 0;
+        loop {
+            let next = req.try_clone();
+            let res = self.client.execute(req).await?;
+            let location = res
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|location| res.url().join(location).ok());
+            let redirect = matches!(
+                res.status(),
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::SEE_OTHER
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            );
+            let (mut next, location) = match (redirect && self.follow_redirects, next, location) {
+                (true, Some(next), Some(location)) => (next, location),
+                _ => return Ok(res),
+            };
+            hops += 1;
+            match self.max_redirects {
+                Some(max) if hops > max => return Err(anyhow!("Exceeded --max-redirects {}", max)),
+                // 与 reqwest 默认的重定向策略一样最多跟随 10 次
+                None if hops > 10 => return Err(anyhow!("Too many redirects (more than 10)")),
+                _ => {}
+            }
+            debug!(status = %res.status(), %location, "redirect");
+            let status = res.status();
+            if status == StatusCode::SEE_OTHER
+                || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+                    && next.method() == Method::POST)
+            {
+                *next.method_mut() = Method::GET;
+                *next.body_mut() = None;
+                for name in [
+                    header::CONTENT_TYPE,
+                    header::CONTENT_LENGTH,
+                    header::CONTENT_ENCODING,
+                ] {
+                    next.headers_mut().remove(name);
+                }
+            }
+            if origin(&location) != requested {
+                for name in [
+                    header::HOST,
+                    header::AUTHORIZATION,
+                    header::COOKIE,
+                    header::PROXY_AUTHORIZATION,
+                    header::WWW_AUTHENTICATE,
+                ] {
+                    next.headers_mut().remove(name);
+                }
+            }
+            *next.url_mut() = location;
+            req = next;
+        }
+    }
+    async fn send(&self, req: Request, progress: &Progress) -> Result<Exchange> {
+        let requested = req.url().clone();
+        let method = req.method().clone();
+        let (headers, body) = self.request_sections;
+        if headers || body {
+            print_request(&req, &self.default_headers, headers, body);
+        }
+        let retries = if self.retry_all || is_idempotent(req.method()) {
+            self.retries
+        } else {
+            0
+        };
+        debug!(method = %req.method(), url = %req.url(), "sending request");
+        *DNS_ELAPSED.lock().unwrap() = None;
+        let start = Instant::now();
+        let mut attempt = 0;
+        let res = loop {
+            // 流式 body 无法复制，只能发送一次
+            progress.attempts.set(progress.attempts.get() + 1);
+            let current = match req.try_clone() {
+                Some(current) => current,
+                None => break self.dispatch(req).await?,
+            };
+            let result = self.dispatch(current).await;
+            let reason = match result {
+                Ok(ref res) if res.status().is_server_error() => res.status().to_string(),
+                Err(ref e)
+                    if e.downcast_ref::<reqwest::Error>()
+                        .is_some_and(|e| e.is_connect() || e.is_timeout()) =>
+                {
+                    e.to_string()
+                }
+                _ => break result?,
+            };
+            let delay = retry_backoff(self.retry_delay, attempt);
+            if attempt >= retries || start.elapsed() + delay > MAX_RETRY_ELAPSED {
+                break result?;
+            }
+            attempt += 1;
+            warn!(attempt, retries, delay_ms = delay.as_millis() as u64, %reason, "retrying");
+            if self.verbose > 0 {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "retry {}/{} in {} ms: {}",
+                        attempt,
+                        retries,
+                        delay.as_millis(),
+                        reason
+                    )
+                    .yellow()
+                );
+            }
+            tokio::time::sleep(delay).await;
+        };
+        progress.responded.set(true);
+        let timing = (start, start.elapsed());
+        if let Some(ref path) = self.history {
+            let entry = HistoryEntry::new(&method, &requested, res.status());
+            if let Err(e) = append_history(path, &entry, MAX_HISTORY_BYTES) {
+                eprintln!("Failed to record history in {}: {:#}", path.display(), e);
+            }
+        }
+        info!(
+            status = %res.status(),
+            version = ?res.version(),
+            url = %res.url(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "response headers received"
+        );
+        let redirected = (*res.url() != requested).then(|| res.url().clone());
+        let tls = (self.verbose_tls && res.url().scheme() == "https").then(|| {
+            res.extensions()
+                .get::<TlsInfo>()
+                .and_then(TlsInfo::peer_certificate)
+                .ok_or_else(|| anyhow!("Server sent no certificate"))
+                .and_then(tls_details)
+        });
+        Ok(Exchange {
+            res,
+            timing,
+            redirected,
+            tls,
+        })
+    }
+    fn finish(&self, (start, headers): (Instant, Duration), redirected: Option<&Url>) {
+        if self.timings {
+            print_phases(headers, start.elapsed());
+        } else if self.meta {
+            print_timing(headers, start.elapsed());
+        }
+        if let (true, Some(url)) = (self.sections.status || self.sections.headers, redirected) {
+            let label = "[redirected to]".bold().truecolor(164, 111, 164);
+            println!("{} {}", label, url.as_str().yellow());
+        }
+    }
+    async fn print_response(&self, res: Response) -> Result<StatusCode> {
+        let status = res.status();
+        if self.download || self.output.is_some() {
+            self.printer.print_head(&res, Sections::default());
+        }
+        match self.output {
+            _ if self.download => download_response(res, self.output.as_deref(), self.quiet).await,
+            Some(ref path) => save_response(res, path, self.quiet).await,
+            None => self.printer.print_response(res, self.sections).await,
+        }?;
+        Ok(status)
+    }
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let mut req = self.client.request(method, url);
+        if !self.query.is_empty() {
+            req = req.query(&self.query);
+        }
+        // hyper 只在没有 Host 头时按 URL 补上，所以显式设置即可覆盖
+        if let Some(ref host) = self.host_header {
+            req = req.header(header::HOST, host.clone());
+        }
+        match self.auth {
+            Some(ref auth) => req.basic_auth(&auth.username, auth.password.as_ref()),
+            None => req,
+        }
+    }
+    fn with_body(&self, req: RequestBuilder, args: &[&BodyField]) -> RequestBuilder {
+        with_fields(req, args, self.form)
+    }
+    fn with_payload(
+        &self,
+        req: RequestBuilder,
+        items: &[RequestItem],
+        raw: &Option<RawBody>,
+    ) -> Result<RequestBuilder> {
+        let (headers, mut fields) = split_items(items);
+        fields.extend(self.data_urlencode.iter());
+        if raw.is_some() && !fields.is_empty() {
+            return Err(anyhow!(
+                "Body fields cannot be combined with --body-file, --stdin, --raw, --data or --clipboard"
+            ));
+        }
+        if raw.is_some() && self.body_template.is_some() {
+            return Err(anyhow!(
+                "--body-template cannot be combined with --body-file, --stdin, --raw, --data or --clipboard"
+            ));
+        }
+        // 原始 body 默认按 JSON 发送，用户显式指定 Content-Type 时以用户为准
+        let content_type = match raw {
+            Some(RawBody::Data(_, content_type)) => content_type,
+            _ => mime::APPLICATION_JSON.as_ref(),
+        };
+        let req = if raw.is_some()
+            && !self.explicit_content_type
+            && !headers.contains_key(header::CONTENT_TYPE)
+        {
+            req.header(header::CONTENT_TYPE, content_type)
+        } else {
+            req
+        };
+        let req = req.headers(headers);
+        Ok(match raw {
+            Some(RawBody::File(path)) => req.body(read_body_file(path)?),
+            Some(RawBody::Stdin) => req.body(read_body(io::stdin().lock())?),
+            Some(RawBody::Json(text) | RawBody::Text(text) | RawBody::Data(text, _)) => {
+                req.body(text.clone())
+            }
+            #[cfg(feature = "clipboard")]
+            Some(RawBody::Clipboard) => req.body(read_clipboard()?),
+            // 与 HTTPie 一致，表单中有 key@path 字段时改用 multipart 上传文件
+            None if self.multipart || (self.form && fields.iter().any(|f| f.file.is_some())) => {
+                req.multipart(multipart_form(&fields)?)
+            }
+            None if self.body_template.is_some() => {
+                let fields = read_text_fields(&fields)?;
+                let fields: Vec<&BodyField> = fields.iter().collect();
+                let mut body = self.body_template.clone().unwrap_or_default();
+                merge_json(&mut body, Value::Object(fields_object(&fields)));
+                req.json(&body)
+            }
+            None => {
+                let fields = read_text_fields(&fields)?;
+                let fields: Vec<&BodyField> = fields.iter().collect();
+                self.with_body(req, &fields)
+            }
+        })
+    }
+    fn prepare(
+        &self,
+        method: Method,
+        url: &str,
+        items: &[RequestItem],
+        raw: &Option<RawBody>,
+    ) -> Result<RequestBuilder> {
+        // POST/PUT/PATCH 没有字段时也发送空 JSON 对象，其余方法只在有 body 时附带
+        let has_body = matches!(method, Method::POST | Method::PUT | Method::PATCH)
+            || raw.is_some()
+            || self.body_template.is_some()
+            || !self.data_urlencode.is_empty()
+            || items
+                .iter()
+                .any(|item| matches!(item, RequestItem::Field(_)));
+        // 只接受 GET/POST 的代理后面，用 POST 发送，真实方法放在 X-HTTP-Method-Override 中
+        let req = if self.method_override && !matches!(method, Method::GET | Method::POST) {
+            self.request(Method::POST, url)
+                .header("X-HTTP-Method-Override", method.as_str())
+        } else {
+            self.request(method, url)
+        };
+        if has_body {
+            self.with_payload(req, items, raw)
+        } else {
+            Ok(req.headers(split_items(items).0))
+        }
+    }
+    fn build(&self, req: RequestBuilder) -> Result<Request> {
+        let mut req = self.build_uncompressed(req)?;
+        if self.compress {
+            compress_body(&mut req)?;
+        }
+        Ok(req)
+    }
+    // 压缩后的 body 不是文本，--save-request 和 --curl 使用压缩前的请求
+    fn build_uncompressed(&self, req: RequestBuilder) -> Result<Request> {
+        let req = req.build()?;
+        if let Some(ref path) = self.save_request {
+            SavedRequest::from_request(&req)?.save(path)?;
+        }
+        Ok(req)
+    }
+    #[tracing::instrument(level = "info", skip_all, fields(%method, url))]
+    pub(crate) async fn execute(
+        &self,
+        method: Method,
+        url: &str,
+        items: &[RequestItem],
+        raw: &Option<RawBody>,
+    ) -> Result<StatusCode> {
+        let head = method == Method::HEAD;
+        let mut req = self.build_uncompressed(self.prepare(method, url, items, raw)?)?;
+        if self.curl {
+            println!("{}", curl_command(&req, &self.default_headers));
+        }
+        if self.compress {
+            compress_body(&mut req)?;
+        }
+        if self.offline {
+            // 同时指定 --curl 时只输出 curl 命令
+            if !self.curl {
+                print_request(&req, &self.default_headers, true, true);
+            }
+            return Ok(StatusCode::OK);
+        }
+        let progress = Progress::default();
+        self.within_deadline(&progress, async {
+            let exchange = self.send(req, &progress).await?;
+            self.respond(exchange, head).await
+        })
+        .await
+    }
+    // --max-time 限制整个请求流程，包括重试、重定向和读取 body
+    async fn within_deadline<T>(
+        &self,
+        progress: &Progress,
+        flow: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let limit = match self.max_time {
+            Some(limit) => limit,
+            None => return flow.await,
+        };
+        match tokio::time::timeout(limit, flow).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!(deadline_message(
+                limit,
+                progress.attempts.get(),
+                progress.responded.get()
+            ))),
+        }
+    }
+    // 只完整输出第一次的响应（--quiet 时不输出），其余只读完 body 并记录耗时
+    pub(crate) async fn repeat(
+        &self,
+        times: u32,
+        method: Method,
+        url: &str,
+        items: &[RequestItem],
+        raw: &Option<RawBody>,
+    ) -> Result<StatusCode> {
+        let head = method == Method::HEAD;
+        let req = self.build(self.prepare(method, url, items, raw)?)?;
+        let mut latencies = Vec::new();
+        let mut status = StatusCode::OK;
+        for i in 0..times {
+            let current = req
+                .try_clone()
+                .ok_or_else(|| anyhow!("--repeat cannot resend a streaming request body"))?;
+            // --max-time 分别限制每一次请求
+            let progress = Progress::default();
+            status = self
+                .within_deadline(&progress, async {
+                    let start = Instant::now();
+                    let exchange = self.send(current, &progress).await?;
+                    let elapsed = start.elapsed();
+                    latencies.push(elapsed);
+                    let status = exchange.res.status();
+                    let label = format!("[{}/{}]", i + 1, times);
+                    println!(
+                        "{} {} {} ms",
+                        label.bold().truecolor(164, 111, 164),
+                        status,
+                        elapsed.as_millis()
+                    );
+                    if i == 0 && !self.quiet {
+                        self.respond(exchange, head).await?;
+                    } else {
+                        read_body_limited(exchange.res, self.max_body_size).await?;
+                    }
+                    Ok(status)
+                })
+                .await?;
+        }
+        println!(
+            "{} {}",
+            "[summary]".bold().truecolor(164, 111, 164),
+            format_latencies(&latencies)
+        );
+        Ok(status)
+    }
+    // 并发请求时先完整读取 body，之后再逐个输出，避免多个响应的内容交错
+    pub(crate) async fn fetch(&self, url: &str) -> Result<Exchange> {
+        let req = self.build(self.prepare(Method::GET, url, &[], &None)?)?;
+        let progress = Progress::default();
+        self.within_deadline(&progress, self.fetch_buffered(req, &progress))
+            .await
+    }
+    // 有差异时返回 1，与 diff 命令一致
+    pub(crate) async fn diff(&self, left: &str, right: &str, headers: bool) -> Result<i32> {
+        let (old, new) = try_join(self.fetch(left), self.fetch(right)).await?;
+        let old = diff_view(old.res, headers).await?;
+        let new = diff_view(new.res, headers).await?;
+        if old == new {
+            println!("{}", "No differences".truecolor(157, 173, 212));
+            return Ok(0);
+        }
+        print_diff(&unified_diff(&old, &new, left, right));
+        Ok(1)
+    }
+    async fn fetch_buffered(&self, req: Request, progress: &Progress) -> Result<Exchange> {
+        let Exchange {
+            res,
+            timing,
+            redirected,
+            tls,
+        } = self.send(req, progress).await?;
+        let mut buffered = http::Response::builder()
+            .status(res.status())
+            .version(res.version());
+        if let Some(headers) = buffered.headers_mut() {
+            headers.extend(res.headers().clone());
+        }
+        let body = read_body_limited(res, self.max_body_size).await?;
+        Ok(Exchange {
+            res: buffered.body(body)?.into(),
+            timing,
+            redirected,
+            tls,
+        })
+    }
+    pub(crate) async fn respond(&self, exchange: Exchange, head: bool) -> Result<StatusCode> {
+        let Exchange {
+            res,
+            timing,
+            redirected,
+            tls,
+        } = exchange;
+        if let Some(details) = tls {
+            print_tls(details);
+        }
+        let status = res.status();
+        if self.fail && (status.is_client_error() || status.is_server_error()) {
+            return Err(anyhow!("HTTP {}", status));
+        }
+        // 成功时什么都不输出，出错时按 --body 输出，退出码由 check_status 给出
+        if self.body_only_on_error && !(status.is_client_error() || status.is_server_error()) {
+            self.finish(timing, redirected.as_ref());
+            return Ok(status);
+        }
+        if self.json {
+            let status = res.status();
+            println!("{}", response_json(res, self.max_body_size).await?);
+            return Ok(status);
+        }
+        if self.porcelain {
+            print_porcelain(res, self.max_body_size).await?;
+            return Ok(status);
+        }
+        if head {
+            // HEAD 响应没有 body，也不写入文件
+            self.printer.print_head(&res, self.sections);
+            self.finish(timing, redirected.as_ref());
+            return Ok(res.status());
+        }
+        let status = self.print_response(res).await?;
+        self.finish(timing, redirected.as_ref());
+        Ok(status)
+    }
+}
+
+// 本次请求（含重定向）DNS 解析的累计耗时，由 TimedResolver 记录
+pub(crate) static DNS_ELAPSED: Mutex<Option<Duration>> = Mutex::new(None);
+
+// --timings 时替换 reqwest 的默认解析器，同样用系统的 getaddrinfo 解析并记录耗时。
+// --resolve 指定的主机和 IP 地址不经过解析器
+struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .collect::<Vec<_>>();
+            let mut elapsed = DNS_ELAPSED.lock().unwrap();
+            *elapsed = Some(elapsed.unwrap_or_default() + start.elapsed());
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn deadline_message(limit: Duration, attempts: u32, responded: bool) -> String {
+    let progress = if responded {
+        "response headers received, body incomplete"
+    } else {
+        "no response received"
+    };
+    format!(
+        "Gave up after --max-time {} s: {} attempt{} sent, {}",
+        limit.as_secs_f64(),
+        attempts,
+        if attempts == 1 { "" } else { "s" },
+        progress
+    )
+}
+
+fn bearer_auth_header(token: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(&format!("Bearer {}", token))
+        .map_err(|e| anyhow!("Invalid bearer token: {}", e))
+}
+
+fn cookie_header(cookies: &[KvPair]) -> Result<HeaderValue> {
+    let value = cookies
+        .iter()
+        .map(|c| format!("{}={}", c.key, c.value))
+        .collect::<Vec<_>>()
+        .join("; ");
+    HeaderValue::from_str(&value).map_err(|e| anyhow!("Invalid cookie {}: {}", value, e))
+}
+
+// 显式的 -H 优先于 --auth-bearer 等快捷参数
+pub(crate) fn build_headers(opts: &Opts) -> Result<header::HeaderMap> {
+    let mut headers = header::HeaderMap::new();
+    if !opts.no_default_headers {
+        headers.insert(header::USER_AGENT, "Rust HTTPIE".parse()?);
+        // 流式输出无法边收边解压，请求服务器不要压缩
+        let accept = if opts.stream {
+            "identity"
+        } else {
+            "gzip, deflate, br"
+        };
+        headers.insert(header::ACCEPT_ENCODING, accept.parse()?);
+    }
+    if let Some(ref agent) = opts.user_agent {
+        let value = HeaderValue::from_str(agent)
+            .map_err(|e| anyhow!("Invalid user agent {}: {}", agent, e))?;
+        headers.insert(header::USER_AGENT, value);
+    }
+    if let Some(ref token) = opts.auth_bearer {
+        headers.insert(header::AUTHORIZATION, bearer_auth_header(token)?);
+    }
+    // 使用会话时 --cookie 交给 SessionStore 合并，否则 reqwest 会因已有 Cookie 头而跳过会话
+    if !opts.cookies.is_empty() && opts.session.is_none() {
+        headers.insert(header::COOKIE, cookie_header(&opts.cookies)?);
+    }
+    if let Some(ref etag) = opts.etag {
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+    }
+    if let Some(ref date) = opts.if_modified_since {
+        headers.insert(header::IF_MODIFIED_SINCE, date.clone());
+    }
+    if opts.accept_json {
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+    }
+    if let Some(ref accept) = opts.accept {
+        headers.insert(header::ACCEPT, accept.clone());
+    }
+    if let Some(ref range) = opts.range {
+        headers.insert(header::RANGE, range.clone());
+    }
+    for (name, value) in opts.headers.iter() {
+        headers.insert(name, value.clone());
+    }
+    Ok(headers)
+}
+
+// Identity::from_pem 需要 reqwest 的 rustls-tls 特性，cert 和 key 拼接在同一个 PEM 缓冲区中
+fn load_identity(cert: &Path, key: Option<&Path>) -> Result<Identity> {
+    let mut pem = fs::read(cert)
+        .with_context(|| format!("Failed to read client certificate {}", cert.display()))?;
+    if let Some(key) = key {
+        pem.push(b'\n');
+        pem.extend(
+            fs::read(key)
+                .with_context(|| format!("Failed to read client key {}", key.display()))?,
+        );
+    }
+    Identity::from_pem(&pem).map_err(|e| anyhow!("Invalid client certificate or key: {}", e))
+}
+
+// 显式读取 HTTP_PROXY/HTTPS_PROXY/ALL_PROXY/NO_PROXY，大写优先
+#[derive(Debug, Default, PartialEq)]
+struct EnvProxy {
+    http: Option<String>,
+    https: Option<String>,
+    all: Option<String>,
+    no_proxy: Vec<String>,
+}
+
+impl EnvProxy {
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| {
+            lookup(name)
+                .or_else(|| lookup(&name.to_lowercase()))
+                .filter(|v| !v.is_empty())
+        };
+        EnvProxy {
+            // CGI 环境下 HTTP_PROXY 可能来自请求头 Proxy:，不能信任
+            http: var("HTTP_PROXY").filter(|_| lookup("REQUEST_METHOD").is_none()),
+            https: var("HTTPS_PROXY"),
+            all: var("ALL_PROXY"),
+            no_proxy: var("NO_PROXY")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().trim_start_matches('.').to_lowercase())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.http.is_none() && self.https.is_none() && self.all.is_none()
+    }
+
+    fn proxy_for(&self, url: &Url) -> Option<String> {
+        let host = url.host_str()?.trim_matches(|c| c == '[' || c == ']');
+        if self
+            .no_proxy
+            .iter()
+            .any(|d| d == "*" || domain_matches(host, d))
+        {
+            return None;
+        }
+        match url.scheme() {
+            "http" => self.http.clone(),
+            "https" => self.https.clone(),
+            _ => None,
+        }
+        .or_else(|| self.all.clone())
+    }
+}
+
+pub(crate) fn build_client(opts: &Opts) -> Result<Client> {
+    let _span = tracing::debug_span!("build_client").entered();
+    debug!(
+        timeout = ?opts.timeout,
+        proxy = ?opts.proxy,
+        no_proxy = opts.no_proxy,
+        insecure = opts.insecure,
+        "building client"
+    );
+    let mut builder = Client::builder()
+        .default_headers(build_headers(opts)?)
+        .tls_info(opts.verbose_tls);
+    if opts.timings {
+        builder = builder.dns_resolver(Arc::new(TimedResolver));
+    }
+    if let Some(ref name) = opts.session {
+        let extra = opts
+            .cookies
+            .iter()
+            .map(|c| (c.key.clone(), c.value.clone()))
+            .collect();
+        let store = SessionStore::load(session_path(name)?, extra);
+        builder = builder.cookie_provider(Arc::new(store));
+    }
+    if let Some(seconds) = opts.timeout {
+        let timeout =
+            positive_seconds(seconds).ok_or_else(|| anyhow!("Invalid --timeout {}", seconds))?;
+        builder = builder.timeout(timeout);
+    }
+    // 不保留空闲连接，每次请求都重新建立，方便观察负载均衡对新连接的处理
+    if opts.no_keepalive {
+        builder = builder.pool_max_idle_per_host(0);
+    }
+    if let Some(addr) = opts.interface {
+        builder = builder.local_address(addr);
+    }
+    // reqwest 按主机名覆盖解析结果，实际连接的端口仍取自 URL
+    for (host, addr) in opts.resolve.iter() {
+        builder = builder.resolve(host, *addr);
+    }
+    if opts.insecure {
+        eprintln!(
+            "{}",
+            "warning: TLS certificate verification is disabled (--insecure)".yellow()
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(ref cert) = opts.cert {
+        builder = builder
+            .use_rustls_tls()
+            .identity(load_identity(cert, opts.key.as_deref())?);
+    }
+    if opts.http1 {
+        builder = builder.http1_only();
+    } else if opts.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    // 关闭 reqwest 自带的系统代理检测，统一由这里决定
+    builder = builder.no_proxy();
+    if let Some(ref proxy) = opts.proxy {
+        let proxy =
+            Proxy::all(proxy.as_str()).with_context(|| format!("Invalid proxy URL {}", proxy))?;
+        builder = builder.proxy(proxy);
+    } else if !opts.no_proxy {
+        let env_proxy = EnvProxy::from_lookup(|name| env::var(name).ok());
+        if !env_proxy.is_empty() {
+            builder = builder.proxy(Proxy::custom(move |url| env_proxy.proxy_for(url)));
+        }
+    }
+    if opts.output.is_none() && !opts.download {
+        builder = builder.no_gzip().no_brotli().no_deflate();
+    }
+    // --host-header 时由 HttpRequest::dispatch 自己跟随重定向
+    if opts.no_follow || opts.host_header.is_some() {
+        builder = builder.redirect(redirect::Policy::none());
+    } else {
+        let max = opts.max_redirects;
+        builder = builder.redirect(redirect::Policy::custom(move |attempt| {
+            debug!(status = %attempt.status(), location = %attempt.url(), "redirect");
+            match max {
+                // Policy::limited 会把原始请求也计入次数，这里按实际跳转次数计算
+                Some(max) if attempt.previous().len() > max => {
+                    attempt.error(format!("Exceeded --max-redirects {}", max))
+                }
+                Some(_) => attempt.follow(),
+                None => redirect::Policy::default().redirect(attempt),
+            }
+        }));
+    }
+    Ok(builder.build()?)
+}
+
+// 与 HTTPie 一致：4xx 返回 4，5xx 返回 5
+pub(crate) fn status_exit_code(status: StatusCode) -> i32 {
+    if status.is_client_error() {
+        4
+    } else if status.is_server_error() {
+        5
+    } else {
+        0
+    }
+}
+
+fn certificate_failure(host: &str, reason: &str) -> String {
+    format!(
+        "TLS certificate verification failed for {}: {} (use --insecure for self-signed certificates)",
+        host, reason
+    )
+}
+
+// 把常见的连接错误翻译成简短的提示，--debug 时保留原始错误作为 Caused by
+pub(crate) fn friendly_error(err: anyhow::Error, opts: &Opts) -> anyhow::Error {
+    let message = match (err.downcast_ref::<reqwest::Error>(), opts.timeout) {
+        (Some(e), Some(seconds)) if e.is_timeout() => {
+            format!("Request timed out after {} seconds", seconds)
+        }
+        (Some(e), _) => match connect_failure(e) {
+            Some(message) => message,
+            None => return err,
+        },
+        _ => return err,
+    };
+    if opts.debug {
+        err.context(message)
+    } else {
+        anyhow!(message)
+    }
+}
+
+// reqwest 不区分连接失败的原因，只能沿错误链查找 hyper、io 和 TLS 库的错误。
+// io::Error::source 会跳过它包装的错误，所以要用 get_ref 取出 TLS 库的错误
+fn connect_failure(err: &reqwest::Error) -> Option<String> {
+    let url = err.url()?;
+    let host = url.host_str().unwrap_or_default();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        if let Some(io_err) = cause.downcast_ref::<io::Error>() {
+            if io_err.kind() == io::ErrorKind::ConnectionRefused {
+                let port = url.port_or_known_default().unwrap_or_default();
+                return Some(format!("Connection refused to {}:{}", host, port));
+            }
+            // reqwest 内部的 rustls 没有导出，按它的错误消息判断
+            if let Some(text) = io_err.get_ref().map(ToString::to_string) {
+                if let Some(reason) = text.strip_prefix("invalid peer certificate: ") {
+                    return Some(certificate_failure(host, reason));
+                }
+            }
+        }
+        // hyper 的 ConnectError 和 native-tls（OpenSSL）的错误没有可匹配的类型，只能按消息判断，
+        // OpenSSL 把校验失败的原因放在末尾的括号里
+        let text = cause.to_string();
+        if text.starts_with("dns error") {
+            return Some(format!("Could not resolve host {}", host));
+        }
+        if text.contains("certificate verify failed") {
+            let reason = text
+                .rsplit_once(" (")
+                .and_then(|(_, reason)| reason.strip_suffix(')'))
+                .unwrap_or("certificate verify failed");
+            return Some(certificate_failure(host, reason));
+        }
+        source = cause.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{
+        data_body, is_query_arg, parse_kv_pair, parse_max_time, parse_method, parse_opts_from,
+        parse_raw_json, parse_request_item, BodyField, Opts, RawBody, RequestItem, SubCommand,
+    };
+    use crate::common::{serve_after, serve_once, serve_sequence, temp_path};
+    use crate::print::{format_timing, print_request, request_headers, PrintSpec, Sections};
+    use clap::IntoApp;
+    use flate2::read::GzDecoder;
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn read_text_fields_reads_files() {
+        let path = temp_path("kv-pair-test.txt");
+        fs::write(&path, "hello").unwrap();
+        let field: BodyField = format!("bio@{}", path.display()).parse().unwrap();
+        assert_eq!(
+            read_text_fields(&[&field]).unwrap(),
+            vec![BodyField {
+                key: "bio".into(),
+                value: Value::String("hello".into()),
+                file: None
+            }]
+        );
+        let missing: BodyField = "bio@/nonexistent/httpie-rust.txt".parse().unwrap();
+        assert!(read_text_fields(&[&missing]).is_err());
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn auth_bearer_works() {
+        assert_eq!(bearer_auth_header("abc").unwrap(), "Bearer abc");
+        assert!(bearer_auth_header("a\nb").is_err());
+        let opts =
+            Opts::try_parse_from(["httpie", "--auth-bearer", "abc", "get", "https://a.b"]).unwrap();
+        let headers = build_headers(&opts).unwrap();
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer abc");
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--auth-bearer",
+            "abc",
+            "-H",
+            "Authorization: Token xyz",
+            "get",
+            "https://a.b",
+        ])
+        .unwrap();
+        let headers = build_headers(&opts).unwrap();
+        assert_eq!(headers[header::AUTHORIZATION], "Token xyz");
+    }
+    #[test]
+    fn basic_auth_is_applied_to_request() {
+        let opts =
+            Opts::try_parse_from(["httpie", "--auth", "user:pass", "get", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http.request(Method::GET, "https://a.b").build().unwrap();
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Basic dXNlcjpwYXNz");
+    }
+    #[test]
+    fn query_is_appended_and_encoded() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "-q",
+            "q=a b",
+            "--query",
+            "amp=&",
+            "get",
+            "https://a.b/search?x=1",
+        ])
+        .unwrap();
+        assert_eq!(opts.query.len(), 2);
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .request(Method::GET, "https://a.b/search?x=1")
+            .build()
+            .unwrap();
+        assert_eq!(req.url().as_str(), "https://a.b/search?x=1&q=a+b&amp=%26");
+    }
+    #[test]
+    fn form_flag_switches_body_encoding() {
+        let field = "a=1".parse::<BodyField>().unwrap();
+        let body = vec![&field];
+        let opts = Opts::try_parse_from(["httpie", "post", "https://a.b", "a=1"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .with_body(http.request(Method::POST, "https://a.b"), &body)
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(req.body().unwrap().as_bytes(), Some(&b"{\"a\":\"1\"}"[..]));
+
+        let opts =
+            Opts::try_parse_from(["httpie", "--form", "post", "https://a.b", "a=1"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .with_body(http.request(Method::POST, "https://a.b"), &body)
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.headers()[header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(req.body().unwrap().as_bytes(), Some(&b"a=1"[..]));
+    }
+    #[test]
+    fn raw_body_content_type_works() {
+        let path = temp_path("raw-body-test.json");
+        fs::write(&path, "[1]").unwrap();
+        let body_file = Some(path.clone());
+        let opts = Opts::try_parse_from(["httpie", "post", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .with_payload(
+                http.request(Method::POST, "https://a.b"),
+                &[],
+                &body_file.clone().map(RawBody::File),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(req.body().unwrap().as_bytes(), Some(&b"[1]"[..]));
+
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "-H",
+            "Content-Type: text/plain",
+            "post",
+            "https://a.b",
+        ])
+        .unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .with_payload(
+                http.request(Method::POST, "https://a.b"),
+                &[],
+                &body_file.clone().map(RawBody::File),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(req.headers().get(header::CONTENT_TYPE).is_none());
+
+        let items = vec![parse_request_item("Content-Type:text/csv").unwrap()];
+        let req = http
+            .with_payload(
+                http.request(Method::POST, "https://a.b"),
+                &items,
+                &body_file.clone().map(RawBody::File),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "text/csv");
+
+        let items = vec![parse_request_item("a=1").unwrap()];
+        assert!(http
+            .with_payload(
+                http.request(Method::POST, "https://a.b"),
+                &items,
+                &body_file.clone().map(RawBody::File),
+            )
+            .is_err());
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn typed_json_body_is_sent() {
+        let opts = Opts::try_parse_from(["httpie", "post", "https://a.b", "a:=3", "b=3"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items = match opts.subcmd {
+            Some(SubCommand::Post(ref args)) => &args.items,
+            _ => unreachable!(),
+        };
+        let req = http
+            .with_payload(http.request(Method::POST, "https://a.b"), items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        let sent: Value = serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(sent, serde_json::json!({"a": 3, "b": "3"}));
+    }
+    #[test]
+    fn request_items_are_split_into_headers_and_body() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "post",
+            "https://a.b",
+            "Accept:text/plain",
+            "X-Tag:1",
+            "X-Tag:2",
+            "a=1",
+            "b:=2",
+        ])
+        .unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items = match opts.subcmd {
+            Some(SubCommand::Post(ref args)) => &args.items,
+            _ => unreachable!(),
+        };
+        let req = http
+            .with_payload(http.request(Method::POST, "https://a.b"), items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()[header::ACCEPT], "text/plain");
+        assert_eq!(req.headers().get_all("x-tag").iter().count(), 2);
+        let sent: Value = serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(sent, serde_json::json!({"a": "1", "b": 2}));
+    }
+    #[tokio::test]
+    async fn timeout_is_reported_friendly() {
+        let (url, _server) = serve_after(
+            Duration::from_secs(2),
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+        );
+        let opts = Opts::try_parse_from(["httpie", "--timeout", "0.2", "get", &url]).unwrap();
+        let client = build_client(&opts).unwrap();
+        let err = client.get(&url).send().await.unwrap_err();
+        let err = friendly_error(err.into(), &opts);
+        assert_eq!(err.to_string(), "Request timed out after 0.2 seconds");
+    }
+    #[tokio::test]
+    async fn no_timeout_by_default() {
+        let (url, _server) = serve_after(
+            Duration::from_millis(300),
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n",
+        );
+        let opts = Opts::try_parse_from(["httpie", "get", &url]).unwrap();
+        let client = build_client(&opts).unwrap();
+        let res = client.get(&url).send().await.unwrap();
+        assert_eq!(res.status(), 204);
+    }
+    #[tokio::test]
+    async fn no_follow_returns_redirect_as_is() {
+        let (url, _server) =
+            serve_once("HTTP/1.1 302 Found\r\nLocation: /elsewhere\r\nContent-Length: 0\r\n\r\n");
+        let opts = Opts::try_parse_from(["httpie", "--no-follow", "get", &url]).unwrap();
+        let res = build_client(&opts).unwrap().get(&url).send().await.unwrap();
+        assert_eq!(res.status(), 302);
+        assert_eq!(res.headers()[header::LOCATION], "/elsewhere");
+    }
+    #[tokio::test]
+    async fn max_redirects_limits_redirects() {
+        let (url, _server) =
+            serve_once("HTTP/1.1 302 Found\r\nLocation: /elsewhere\r\nContent-Length: 0\r\n\r\n");
+        let opts = Opts::try_parse_from(["httpie", "--max-redirects", "0", "get", &url]).unwrap();
+        let err = build_client(&opts)
+            .unwrap()
+            .get(&url)
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_redirect());
+        assert!(Opts::try_parse_from([
+            "httpie",
+            "--max-redirects",
+            "3",
+            "--no-follow",
+            "get",
+            "https://a.b"
+        ])
+        .is_err());
+    }
+    #[tokio::test]
+    async fn output_saves_raw_body() {
+        let (url, _server) = serve_once("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 4\r\n\r\n\x00\x01\x7f\n",
+        );
+        let path = temp_path("output-test.bin");
+        let opts =
+            Opts::try_parse_from(["httpie", "-o", path.to_str().unwrap(), "get", &url]).unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        match opts.subcmd {
+            Some(SubCommand::Get(ref args)) => {
+                let status = http.execute(Method::GET, &args.url[0], &[], &None);
+                assert_eq!(status.await.unwrap(), StatusCode::OK)
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"\x00\x01\x7f\n");
+        fs::remove_file(path).unwrap();
+    }
+    #[tokio::test]
+    async fn download_streams_body_to_file() {
+        let (url, _server) = serve_once(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n",
+        );
+        let path = temp_path("download-test.txt");
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--download",
+            "-o",
+            path.to_str().unwrap(),
+            "get",
+            &url,
+        ])
+        .unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        match opts.subcmd {
+            Some(SubCommand::Get(ref args)) => {
+                let status = http.execute(Method::GET, &args.url[0], &[], &None);
+                assert_eq!(status.await.unwrap(), StatusCode::OK)
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"abcde");
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn verbose_request_includes_default_headers() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "-v",
+            "-H",
+            "User-Agent: custom",
+            "post",
+            "https://a.b",
+            "X-Item:1",
+            "a=1",
+        ])
+        .unwrap();
+        assert_eq!(opts.verbose, 1);
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items = match opts.subcmd {
+            Some(SubCommand::Post(ref args)) => &args.items,
+            _ => unreachable!(),
+        };
+        let req = http
+            .with_payload(http.request(Method::POST, "https://a.b"), items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        let headers = request_headers(&req, &http.default_headers);
+        assert!(!headers.contains_key("x-powered-by"));
+        assert_eq!(headers[header::USER_AGENT], "custom");
+        assert_eq!(headers["x-item"], "1");
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+        print_request(&req, &http.default_headers, true, true);
+    }
+    #[test]
+    fn cookie_option_builds_cookie_header() {
+        let cookies = vec![
+            parse_kv_pair("a=1").unwrap(),
+            parse_kv_pair("b=two").unwrap(),
+        ];
+        assert_eq!(cookie_header(&cookies).unwrap(), "a=1; b=two");
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--cookie",
+            "a=1",
+            "--cookie",
+            "b=2",
+            "get",
+            "https://a.b",
+        ])
+        .unwrap();
+        assert_eq!(build_headers(&opts).unwrap()[header::COOKIE], "a=1; b=2");
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--cookie",
+            "a=1",
+            "-H",
+            "Cookie: c=3",
+            "get",
+            "https://a.b",
+        ])
+        .unwrap();
+        assert_eq!(build_headers(&opts).unwrap()[header::COOKIE], "c=3");
+    }
+    #[test]
+    fn insecure_flag_works() {
+        let opts = Opts::try_parse_from(["httpie", "-k", "get", "https://self-signed.badssl.com"])
+            .unwrap();
+        assert!(opts.insecure);
+        assert!(build_client(&opts).is_ok());
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        assert!(!opts.insecure);
+    }
+    #[test]
+    fn load_identity_reports_errors() {
+        let err = load_identity(Path::new("/nonexistent/client.pem"), None).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/client.pem"));
+        let path = temp_path("invalid-cert.pem");
+        fs::write(&path, "not a pem").unwrap();
+        let err = load_identity(&path, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid client certificate or key"));
+        let err = load_identity(&path, Some(Path::new("/nonexistent/client.key"))).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/client.key"));
+        fs::remove_file(path).unwrap();
+        assert!(
+            Opts::try_parse_from(["httpie", "--key", "client.key", "get", "https://a.b"]).is_err()
+        );
+    }
+    #[test]
+    fn env_proxy_reads_variables() {
+        let vars: HashMap<&str, &str> = [
+            ("HTTP_PROXY", "http://upper:3128"),
+            ("http_proxy", "http://lower:3128"),
+            ("https_proxy", "http://secure:3128"),
+            ("ALL_PROXY", "socks5://127.0.0.1:1080"),
+            ("NO_PROXY", "localhost, .internal.example"),
+        ]
+        .into_iter()
+        .collect();
+        let env_proxy = EnvProxy::from_lookup(|name| vars.get(name).map(|v| v.to_string()));
+        let proxy_for = |url: &str| env_proxy.proxy_for(&Url::parse(url).unwrap());
+        assert_eq!(
+            proxy_for("http://a.b/").as_deref(),
+            Some("http://upper:3128")
+        );
+        assert_eq!(
+            proxy_for("https://a.b/").as_deref(),
+            Some("http://secure:3128")
+        );
+        assert_eq!(
+            proxy_for("ftp://a.b/").as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(proxy_for("http://localhost:8080/"), None);
+        assert_eq!(proxy_for("https://api.internal.example/"), None);
+        assert!(EnvProxy::from_lookup(|_| None).is_empty());
+        let cgi = EnvProxy::from_lookup(|name| match name {
+            "HTTP_PROXY" => Some("http://evil:80".into()),
+            "REQUEST_METHOD" => Some("GET".into()),
+            _ => None,
+        });
+        assert!(cgi.is_empty());
+    }
+    #[tokio::test]
+    async fn proxy_option_routes_requests() {
+        let (proxy, _server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 7\r\n\r\nproxied");
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--proxy",
+            &proxy,
+            "get",
+            "http://example.invalid/",
+        ])
+        .unwrap();
+        let body = build_client(&opts)
+            .unwrap()
+            .get("http://example.invalid/")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied");
+        let opts =
+            Opts::try_parse_from(["httpie", "--proxy", "::bad", "get", "http://a.b/"]).unwrap();
+        assert!(build_client(&opts).is_err());
+        assert!(Opts::try_parse_from([
+            "httpie",
+            "--proxy",
+            &proxy,
+            "--no-proxy",
+            "get",
+            "http://a.b/"
+        ])
+        .is_err());
+    }
+    #[test]
+    fn timing_is_opt_in() {
+        assert_eq!(
+            format_timing(Duration::from_millis(120), Duration::from_millis(312)),
+            "312 ms (headers 120 ms)"
+        );
+        let client = Client::new();
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        assert!(!HttpRequest::new(client.clone(), &opts).unwrap().meta);
+        let opts = Opts::try_parse_from(["httpie", "--meta", "get", "https://a.b"]).unwrap();
+        assert!(HttpRequest::new(client.clone(), &opts).unwrap().meta);
+        let opts = Opts::try_parse_from(["httpie", "-vv", "get", "https://a.b"]).unwrap();
+        assert!(HttpRequest::new(client, &opts).unwrap().meta);
+    }
+    #[test]
+    fn status_exit_code_works() {
+        assert_eq!(status_exit_code(StatusCode::OK), 0);
+        assert_eq!(status_exit_code(StatusCode::FOUND), 0);
+        assert_eq!(status_exit_code(StatusCode::NOT_FOUND), 4);
+        assert_eq!(status_exit_code(StatusCode::BAD_GATEWAY), 5);
+    }
+    #[test]
+    fn retry_backoff_doubles() {
+        assert_eq!(retry_backoff(100, 0), Duration::from_millis(100));
+        assert_eq!(retry_backoff(100, 3), Duration::from_millis(800));
+        assert!(retry_backoff(u64::MAX, 40) >= MAX_RETRY_ELAPSED);
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+    }
+    #[tokio::test]
+    async fn retries_server_errors() {
+        const UNAVAILABLE: &str =
+            "HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+        const OK: &str = "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+        let url = serve_sequence(vec![UNAVAILABLE, UNAVAILABLE, OK]);
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--retries",
+            "2",
+            "--retry-delay",
+            "1",
+            "head",
+            &url,
+        ])
+        .unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        let res = http
+            .send(
+                http.request(Method::HEAD, &url).build().unwrap(),
+                &Progress::default(),
+            )
+            .await
+            .unwrap()
+            .res;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // POST 默认不重试
+        let url = serve_sequence(vec![UNAVAILABLE, OK]);
+        let res = http
+            .send(
+                http.request(Method::POST, &url).build().unwrap(),
+                &Progress::default(),
+            )
+            .await
+            .unwrap()
+            .res;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let url = serve_sequence(vec![UNAVAILABLE, OK]);
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--retries",
+            "1",
+            "--retry-delay",
+            "1",
+            "--retry-all",
+            "post",
+            &url,
+        ])
+        .unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        let res = http
+            .send(
+                http.request(Method::POST, &url).build().unwrap(),
+                &Progress::default(),
+            )
+            .await
+            .unwrap()
+            .res;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    fn prepared(method: Method, items: &[&str]) -> Request {
+        let opts = Opts::try_parse_from(["httpie", "get", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items: Vec<RequestItem> = items.iter().map(|s| s.parse().unwrap()).collect();
+        http.prepare(method, "https://a.b/", &items, &None)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+    fn body_of(req: &Request) -> Option<&[u8]> {
+        req.body().and_then(|b| b.as_bytes())
+    }
+    #[test]
+    fn prepare_get() {
+        let req = prepared(Method::GET, &[]);
+        assert_eq!(req.method(), Method::GET);
+        assert!(req.body().is_none());
+    }
+    #[test]
+    fn prepare_post() {
+        let req = prepared(Method::POST, &["a=1"]);
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(body_of(&req), Some(&br#"{"a":"1"}"#[..]));
+        assert_eq!(body_of(&prepared(Method::POST, &[])), Some(&b"{}"[..]));
+    }
+    #[test]
+    fn prepare_put() {
+        let req = prepared(Method::PUT, &["X-A:1", "a:=1"]);
+        assert_eq!(req.method(), Method::PUT);
+        assert_eq!(req.headers()["x-a"], "1");
+        assert_eq!(body_of(&req), Some(&br#"{"a":1}"#[..]));
+    }
+    #[test]
+    fn prepare_patch() {
+        let req = prepared(Method::PATCH, &[]);
+        assert_eq!(req.method(), Method::PATCH);
+        assert_eq!(body_of(&req), Some(&b"{}"[..]));
+    }
+    #[test]
+    fn prepare_delete() {
+        let req = prepared(Method::DELETE, &["X-A:1"]);
+        assert_eq!(req.method(), Method::DELETE);
+        assert_eq!(req.headers()["x-a"], "1");
+        assert!(req.body().is_none());
+        let req = prepared(Method::DELETE, &["id=1"]);
+        assert_eq!(body_of(&req), Some(&br#"{"id":"1"}"#[..]));
+    }
+    #[test]
+    fn prepare_head() {
+        let req = prepared(Method::HEAD, &[]);
+        assert_eq!(req.method(), Method::HEAD);
+        assert!(req.body().is_none());
+    }
+    #[test]
+    fn prepare_options() {
+        let req = prepared(Method::OPTIONS, &[]);
+        assert_eq!(req.method(), Method::OPTIONS);
+        assert!(req.body().is_none());
+    }
+    #[test]
+    fn raw_json_body() {
+        assert!(parse_raw_json("[1, 2, 3]").is_ok());
+        assert!(parse_raw_json("{\"a\": [1, {\"b\": null}]}").is_ok());
+        let err = parse_raw_json("[1, 2").unwrap_err();
+        assert!(err.to_string().contains("Invalid JSON for --raw"));
+        assert!(Opts::try_parse_from(["httpie", "put", "https://a.b", "--raw", "[1"]).is_err());
+        assert!(
+            Opts::try_parse_from(["httpie", "patch", "https://a.b", "--raw", "[]", "--stdin"])
+                .is_err()
+        );
+
+        let opts =
+            Opts::try_parse_from(["httpie", "put", "https://a.b", "--raw", "[1,2,3]"]).unwrap();
+        let raw = match opts.subcmd {
+            Some(SubCommand::Put(ref args)) => args.body.raw_body(),
+            _ => unreachable!(),
+        };
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .prepare(
+                Method::PUT,
+                "https://a.b",
+                &["X-A:1".parse().unwrap()],
+                &raw,
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(req.body().unwrap().as_bytes(), Some(&b"[1,2,3]"[..]));
+        assert!(http
+            .prepare(Method::PUT, "https://a.b", &["a=1".parse().unwrap()], &raw)
+            .is_err());
+    }
+    #[test]
+    fn multipart_uploads_files() {
+        let image = temp_path("upload.png");
+        fs::write(&image, b"\x89PNG\r\n").unwrap();
+        let items = vec![
+            parse_request_item("title=cat").unwrap(),
+            parse_request_item(&format!("photo@{}", image.display())).unwrap(),
+        ];
+        // 二进制文件不能放进 JSON
+        let opts = Opts::try_parse_from(["httpie", "post", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let err = http
+            .prepare(Method::POST, "https://a.b", &items, &None)
+            .unwrap_err();
+        assert!(err.to_string().contains("uploaded as multipart"));
+
+        let opts = Opts::try_parse_from(["httpie", "--multipart", "post", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .prepare(Method::POST, "https://a.b", &items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        let content_type = req.headers()[header::CONTENT_TYPE].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+        fs::remove_file(&image).unwrap();
+        assert!(
+            Opts::try_parse_from(["httpie", "--multipart", "-f", "post", "https://a.b"]).is_err()
+        );
+    }
+    #[tokio::test]
+    async fn multipart_body_contains_parts() {
+        let path = temp_path("upload.txt");
+        fs::write(&path, "hello").unwrap();
+        let field = parse_request_item(&format!("doc@{}", path.display())).unwrap();
+        let text = parse_request_item("n:=1").unwrap();
+        let fields: Vec<&BodyField> = [&field, &text]
+            .iter()
+            .filter_map(|item| match item {
+                RequestItem::Field(f) => Some(f),
+                _ => None,
+            })
+            .collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&request).ends_with("--\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let form = multipart_form(&fields).unwrap();
+        Client::new()
+            .post(&url)
+            .multipart(form)
+            .send()
+            .await
+            .unwrap();
+        let request = server.join().unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(request.contains(&format!(
+            "Content-Disposition: form-data; name=\"doc\"; filename=\"{}\"",
+            name
+        )));
+        assert!(request.contains("Content-Type: text/plain\r\n\r\nhello"));
+        assert!(request.contains("name=\"n\"\r\n\r\n1"));
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn request_subcommand_accepts_custom_methods() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "request",
+            "--method",
+            "PROPFIND",
+            "https://dav.example.com/files/",
+            "Depth:1",
+        ])
+        .unwrap();
+        let args = match opts.subcmd {
+            Some(SubCommand::Request(ref args)) => args,
+            _ => unreachable!(),
+        };
+        assert_eq!(args.method.as_str(), "PROPFIND");
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .prepare(args.method.clone(), &args.url, &args.items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.method().as_str(), "PROPFIND");
+        assert_eq!(req.headers()["depth"], "1");
+        assert!(req.body().is_none());
+        let raw = Some(RawBody::Json("{}".into()));
+        let req = http
+            .prepare(args.method.clone(), &args.url, &[], &raw)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.body().unwrap().as_bytes(), Some(&b"{}"[..]));
+
+        assert!(parse_method("REPORT").is_ok());
+        assert!(parse_method("BAD VERB").is_err());
+        assert!(parse_method("").is_err());
+        assert!(Opts::try_parse_from(["httpie", "request", "https://a.b"]).is_err());
+    }
+    #[test]
+    fn user_agent_can_be_overridden() {
+        let headers = |args: &[&str]| {
+            let opts =
+                Opts::try_parse_from(["httpie"].iter().chain(args).chain(&["get", "https://a.b"]))
+                    .unwrap();
+            build_headers(&opts).unwrap()
+        };
+        assert_eq!(headers(&[])[header::USER_AGENT], "Rust HTTPIE");
+        assert_eq!(
+            headers(&["--user-agent", "probe/1.0"])[header::USER_AGENT],
+            "probe/1.0"
+        );
+        // -H 显式指定的请求头优先
+        let explicit = headers(&["--user-agent", "a", "-H", "User-Agent: b"]);
+        assert_eq!(explicit[header::USER_AGENT], "b");
+        assert!(headers(&["--no-default-headers"]).is_empty());
+        let only_agent = headers(&["--no-default-headers", "--user-agent", "probe"]);
+        assert_eq!(only_agent.len(), 1);
+        assert_eq!(only_agent[header::USER_AGENT], "probe");
+    }
+    #[tokio::test]
+    async fn records_final_url_after_redirects() {
+        let url = serve_sequence(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /final\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let opts = Opts::try_parse_from(["httpie", "get", &url]).unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        let exchange = http
+            .send(
+                http.request(Method::GET, &url).build().unwrap(),
+                &Progress::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(exchange.res.status(), StatusCode::OK);
+        let expected = format!("{}/final", url);
+        assert_eq!(
+            exchange.redirected.as_ref().map(Url::as_str),
+            Some(expected.as_str())
+        );
+
+        // --no-follow 时停在 3xx，Location 保留在响应头中
+        let url = serve_sequence(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /final\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let opts = Opts::try_parse_from(["httpie", "--no-follow", "get", &url]).unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        let exchange = http
+            .send(
+                http.request(Method::GET, &url).build().unwrap(),
+                &Progress::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(exchange.res.status(), StatusCode::FOUND);
+        assert_eq!(exchange.res.headers()[header::LOCATION], "/final");
+        assert!(exchange.redirected.is_none());
+    }
+    #[tokio::test]
+    async fn concurrent_fetches_keep_their_own_exchange() {
+        let redirect =
+            "HTTP/1.1 302 Found\r\nLocation: /final\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+        let ok = "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+        let left = serve_sequence(vec![redirect, ok]);
+        let right = serve_sequence(vec![ok]);
+        let opts = Opts::try_parse_from(["httpie", "get", &left]).unwrap();
+        let http = HttpRequest::new(build_client(&opts).unwrap(), &opts).unwrap();
+        let (left_exchange, right_exchange) = try_join(http.fetch(&left), http.fetch(&right))
+            .await
+            .unwrap();
+        let expected = format!("{}/final", left);
+        assert_eq!(
+            left_exchange.redirected.as_ref().map(Url::as_str),
+            Some(expected.as_str())
+        );
+        assert!(right_exchange.redirected.is_none());
+    }
+    #[test]
+    fn saved_request_round_trips() {
+        let req = Client::new()
+            .post("https://a.b/items?q=1")
+            .header("X-Token", "t")
+            .json(&serde_json::json!({"name": "x"}))
+            .build()
+            .unwrap();
+        let saved = SavedRequest::from_request(&req).unwrap();
+        assert_eq!(
+            saved,
+            SavedRequest {
+                method: "POST".into(),
+                url: "https://a.b/items?q=1".into(),
+                headers: vec![
+                    ("x-token".into(), "t".into()),
+                    ("content-type".into(), "application/json".into()),
+                ],
+                body: Some("{\"name\":\"x\"}".into()),
+            }
+        );
+        let path = temp_path("saved-request.json");
+        saved.save(&path).unwrap();
+        assert_eq!(SavedRequest::load(&path).unwrap(), saved);
+
+        // 重放时命令行的同名请求头覆盖保存的请求头
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "replay",
+            path.to_str().unwrap(),
+            "-X",
+            "PUT",
+            "X-Token:new",
+        ])
+        .unwrap();
+        let args = match opts.subcmd {
+            Some(SubCommand::Replay(ref args)) => args,
+            _ => unreachable!(),
+        };
+        assert_eq!(args.method, Some(Method::PUT));
+        let items = args.request_items().unwrap();
+        let (headers, _) = split_items(&items);
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["x-token"], "new");
+        fs::remove_file(&path).unwrap();
+        assert!(SavedRequest::load(&path).is_err());
+    }
+    #[test]
+    fn print_spec_covers_every_combination() {
+        for mask in 1..16u8 {
+            let spec: String = "HBhb"
+                .chars()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, c)| c)
+                .collect();
+            let parsed: PrintSpec = spec.parse().unwrap();
+            assert_eq!(
+                parsed,
+                PrintSpec {
+                    request_headers: mask & 1 != 0,
+                    request_body: mask & 2 != 0,
+                    response_headers: mask & 4 != 0,
+                    response_body: mask & 8 != 0,
+                },
+                "{}",
+                spec
+            );
+        }
+        assert!("".parse::<PrintSpec>().is_err());
+        assert!("hx".parse::<PrintSpec>().is_err());
+
+        let http = |args: &[&str]| {
+            let opts =
+                Opts::try_parse_from(["httpie"].iter().chain(args).chain(&["get", "https://a.b"]))
+                    .unwrap();
+            let http = HttpRequest::new(Client::new(), &opts).unwrap();
+            (http.request_sections, http.sections)
+        };
+        let all = Sections::default();
+        assert_eq!(http(&[]), ((false, false), all));
+        assert_eq!(http(&["-v"]), ((true, true), all));
+        assert_eq!(http(&["--print", "hb"]), ((false, false), all));
+        assert_eq!(
+            http(&["-p", "Hb"]),
+            (
+                (true, false),
+                Sections {
+                    status: false,
+                    headers: false,
+                    body: true
+                }
+            )
+        );
+        assert_eq!(
+            http(&["-v", "-p", "Bh"]),
+            (
+                (false, true),
+                Sections {
+                    status: true,
+                    headers: true,
+                    body: false
+                }
+            )
+        );
+        assert!(
+            Opts::try_parse_from(["httpie", "-p", "b", "--body", "get", "https://a.b"]).is_err()
+        );
+    }
+    #[tokio::test]
+    async fn http_version_flags_configure_client() {
+        const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+        let client = |flag: &str, url: &str| {
+            let opts = Opts::try_parse_from(["httpie", flag, "get", url]).unwrap();
+            build_client(&opts).unwrap()
+        };
+        let (url, _server) = serve_once(OK);
+        let res = client("--http1", &url).get(&url).send().await.unwrap();
+        assert_eq!(res.version(), reqwest::Version::HTTP_11);
+        // 服务器只会 HTTP/1.1，直接发送 HTTP/2 前言会失败
+        let (url, _server) = serve_once(OK);
+        let err = client("--http2-prior-knowledge", &url)
+            .get(&url)
+            .send()
+            .await;
+        assert!(err.is_err());
+        assert!(Opts::try_parse_from([
+            "httpie",
+            "--http1",
+            "--http2-prior-knowledge",
+            "get",
+            "https://a.b"
+        ])
+        .is_err());
+    }
+    #[test]
+    fn merge_json_overrides_and_merges() {
+        let mut base = serde_json::json!({
+            "name": "base",
+            "tags": ["a"],
+            "owner": {"id": 1, "role": "admin"},
+            "limits": {"cpu": 1}
+        });
+        merge_json(
+            &mut base,
+            serde_json::json!({
+                "name": "new",
+                "tags": ["b"],
+                "owner": {"role": "viewer", "team": "x"},
+                "limits": 0,
+                "extra": true
+            }),
+        );
+        assert_eq!(
+            base,
+            serde_json::json!({
+                "name": "new",
+                "tags": ["b"],
+                "owner": {"id": 1, "role": "viewer", "team": "x"},
+                "limits": 0,
+                "extra": true
+            })
+        );
+        let mut scalar = serde_json::json!(1);
+        merge_json(&mut scalar, serde_json::json!({"a": 1}));
+        assert_eq!(scalar, serde_json::json!({"a": 1}));
+    }
+    #[test]
+    fn body_template_applies_field_overrides() {
+        let path = temp_path("body-template.json");
+        fs::write(&path, r#"{"name":"base","meta":{"v":1,"w":2}}"#).unwrap();
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--body-template",
+            path.to_str().unwrap(),
+            "put",
+            "https://a.b",
+            "name=tweaked",
+            "meta:={\"w\":3}",
+        ])
+        .unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items = match opts.subcmd {
+            Some(SubCommand::Put(ref args)) => &args.items,
+            _ => unreachable!(),
+        };
+        let req = http
+            .prepare(Method::PUT, "https://a.b", items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        let body: Value = serde_json::from_slice(body_of(&req).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"name": "tweaked", "meta": {"v": 1, "w": 3}})
+        );
+        let raw = Some(RawBody::Json("{}".into()));
+        assert!(http.prepare(Method::PUT, "https://a.b", &[], &raw).is_err());
+
+        fs::write(&path, "[1]").unwrap();
+        assert!(HttpRequest::new(Client::new(), &opts).is_err());
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn get_key_value_args_become_query() {
+        let matches = Opts::into_app()
+            .try_get_matches_from(["httpie", "get", "https://a.b/p", "a=1", "b=2"])
+            .unwrap();
+        let opts = parse_opts_from(&matches, None).unwrap();
+        assert_eq!(opts.subcmd.as_ref().unwrap().urls(), ["https://a.b/p"]);
+        let req = HttpRequest::new(Client::new(), &opts)
+            .unwrap()
+            .request(Method::GET, "https://a.b/p")
+            .build()
+            .unwrap();
+        assert_eq!(req.url().as_str(), "https://a.b/p?a=1&b=2");
+
+        assert!(is_query_arg("a=1"));
+        assert!(!is_query_arg("https://a.b/?a=1"));
+        assert!(!is_query_arg("a.b/p?x=1"));
+        let matches = Opts::into_app()
+            .try_get_matches_from(["httpie", "get", "a=1", "https://a.b"])
+            .unwrap();
+        assert!(parse_opts_from(&matches, None).is_err());
+    }
+    #[test]
+    fn compress_gzips_the_request_body() {
+        let opts =
+            Opts::try_parse_from(["httpie", "--compress", "post", "https://a.b", "a=1"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items = match opts.subcmd {
+            Some(SubCommand::Post(ref args)) => &args.items,
+            _ => unreachable!(),
+        };
+        let req = http
+            .build(
+                http.prepare(Method::POST, "https://a.b", items, &None)
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(req.headers()[header::CONTENT_ENCODING], "gzip");
+        let mut body = String::new();
+        GzDecoder::new(body_of(&req).unwrap())
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, r#"{"a":"1"}"#);
+
+        let req = http
+            .build(
+                http.prepare(Method::GET, "https://a.b", &[], &None)
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(req.headers().get(header::CONTENT_ENCODING).is_none());
+        assert!(req.body().is_none());
+    }
+    #[test]
+    fn data_urlencode_encodes_reserved_characters() {
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--data-urlencode",
+            "q=a&b=c d",
+            "--data-urlencode",
+            "next=/p?x=1#top",
+            "post",
+            "https://a.b",
+            "n=1",
+        ])
+        .unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items = match opts.subcmd {
+            Some(SubCommand::Post(ref args)) => &args.items,
+            _ => unreachable!(),
+        };
+        let req = http
+            .prepare(Method::POST, "https://a.b", items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.headers()[header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(
+            body_of(&req).unwrap(),
+            b"n=1&q=a%26b%3Dc+d&next=%2Fp%3Fx%3D1%23top"
+        );
+        assert!(Opts::try_parse_from([
+            "httpie",
+            "--multipart",
+            "--data-urlencode",
+            "a=1",
+            "post",
+            "https://a.b"
+        ])
+        .is_err());
+    }
+    #[test]
+    fn repeated_body_keys_become_arrays() {
+        let fields = [
+            "tag=a",
+            "name=x",
+            "tag=b",
+            "list:=[1]",
+            "list:=[2]",
+            "tag=c",
+        ]
+        .iter()
+        .map(|s| s.parse::<BodyField>().unwrap())
+        .collect::<Vec<_>>();
+        let fields = fields.iter().collect::<Vec<_>>();
+        assert_eq!(
+            Value::Object(fields_object(&fields)),
+            serde_json::json!({"tag": ["a", "b", "c"], "name": "x", "list": [[1], [2]]})
+        );
+    }
+    #[test]
+    fn data_chunks_join_by_mode() {
+        let chunks = ["{\"a\":".to_string(), "1}".to_string()];
+        let raw = Some(data_body(&chunks, false));
+        let opts = Opts::try_parse_from(["httpie", "post", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .prepare(Method::POST, "https://a.b", &[], &raw)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(body_of(&req).unwrap(), b"{\"a\":1}");
+        assert_eq!(req.headers()[header::CONTENT_TYPE], "application/json");
+
+        let chunks = ["a=1".to_string(), "b=x%26y".to_string()];
+        let req = http
+            .prepare(
+                Method::POST,
+                "https://a.b",
+                &[],
+                &Some(data_body(&chunks, true)),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(body_of(&req).unwrap(), b"a=1&b=x%26y");
+        assert_eq!(
+            req.headers()[header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        let req = http
+            .prepare(
+                Method::POST,
+                "https://a.b",
+                &[],
+                &Some(data_body(&chunks, false)),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(body_of(&req).unwrap(), b"a=1b=x%26y");
+        assert_eq!(
+            req.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+
+        let items = [parse_request_item("c=3").unwrap()];
+        let raw = Some(data_body(&chunks, true));
+        assert!(http
+            .prepare(Method::POST, "https://a.b", &items, &raw)
+            .is_err());
+    }
+    #[test]
+    fn max_time_reports_progress() {
+        assert_eq!(parse_max_time("1.5").unwrap(), Duration::from_millis(1500));
+        for bad in ["0", "-1", "abc", "inf", "nan", "1e30"] {
+            assert!(parse_max_time(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            deadline_message(Duration::from_millis(500), 1, false),
+            "Gave up after --max-time 0.5 s: 1 attempt sent, no response received"
+        );
+        assert_eq!(
+            deadline_message(Duration::from_secs(2), 3, true),
+            "Gave up after --max-time 2 s: 3 attempts sent, response headers received, body incomplete"
+        );
+    }
+    #[test]
+    fn method_override_sends_post_with_header() {
+        let opts =
+            Opts::try_parse_from(["httpie", "--method-override", "get", "https://a.b"]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let items: Vec<RequestItem> = vec!["a=1".parse().unwrap()];
+        let req = http
+            .prepare(Method::PUT, "https://a.b/", &items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(req.headers()["x-http-method-override"], "PUT");
+        assert_eq!(body_of(&req), Some(&br#"{"a":"1"}"#[..]));
+        let req = http
+            .prepare(Method::DELETE, "https://a.b/", &[], &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(req.headers()["x-http-method-override"], "DELETE");
+        let req = http
+            .prepare(Method::GET, "https://a.b/", &[], &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.method(), Method::GET);
+        assert!(!req.headers().contains_key("x-http-method-override"));
+    }
+    #[tokio::test]
+    async fn form_with_file_fields_becomes_multipart() {
+        let path = temp_path("form-upload.txt");
+        fs::write(&path, "report").unwrap();
+        let items = vec![
+            parse_request_item("title=weekly").unwrap(),
+            parse_request_item(&format!("attachment@{}", path.display())).unwrap(),
+        ];
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&request).ends_with("--\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let opts = Opts::try_parse_from(["httpie", "--form", "post", &url]).unwrap();
+        let http = HttpRequest::new(Client::new(), &opts).unwrap();
+        let req = http
+            .prepare(Method::POST, &url, &items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        let content_type = req.headers()[header::CONTENT_TYPE].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+        http.client.execute(req).await.unwrap();
+        let request = server.join().unwrap();
+        assert!(request.contains("name=\"title\"\r\n\r\nweekly"));
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(request.contains(&format!(
+            "name=\"attachment\"; filename=\"{}\"\r\nContent-Type: text/plain\r\n\r\nreport",
+            name
+        )));
+        fs::remove_file(&path).unwrap();
+
+        // 没有文件字段时仍是普通表单
+        let items = vec![parse_request_item("title=weekly").unwrap()];
+        let req = http
+            .prepare(Method::POST, &url, &items, &None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.headers()[header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        let items = vec![parse_request_item("attachment@/no/such/file").unwrap()];
+        let err = http.prepare(Method::POST, &url, &items, &None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Failed to read file /no/such/file"));
+    }
+}
@@ -0,0 +1,303 @@
+//! `--session` 的 cookie 持久化
+
+use anyhow::{anyhow, Result};
+use reqwest::cookie::CookieStore;
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use url::Url;
+
+use crate::cli::config_dir;
+
+pub(crate) fn session_path(name: &str) -> Result<PathBuf> {
+    if name.contains(['/', '\\']) {
+        return Ok(PathBuf::from(name));
+    }
+    config_dir()
+        .map(|dir| dir.join("sessions").join(format!("{}.json", name)))
+        .ok_or_else(|| anyhow!("Cannot locate a config directory for session {}", name))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SessionCookie {
+    name: String,
+    value: String,
+    domain: String,
+    path: String,
+    // 没有 Domain 属性时只发回设置它的主机，不发给子域名；旧的会话文件没有这两个字段
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default)]
+    secure: bool,
+}
+
+// 返回解析出的 cookie 以及它是否已过期（服务器删除 cookie 的常见写法）。
+// Domain 不覆盖请求的主机时拒绝整个 cookie，避免一个站点给其他站点种 cookie
+fn parse_set_cookie(header: &str, url: &Url) -> Option<(SessionCookie, bool)> {
+    let host = url.host_str()?;
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let mut cookie = SessionCookie {
+        name: name.trim().to_string(),
+        value: value.trim().to_string(),
+        domain: host.to_string(),
+        path: "/".to_string(),
+        host_only: true,
+        secure: false,
+    };
+    let mut expired = false;
+    for attr in parts {
+        let (key, value) = attr.split_once('=').unwrap_or((attr, ""));
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                // 至少要有两级，Domain=com 这样的顶级域名不接受
+                if !domain_matches(host, &domain) || (domain != host && !domain.contains('.')) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "secure" => cookie.secure = true,
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "max-age" => expired = value.parse::<i64>().is_ok_and(|age| age <= 0),
+            "expires" => {
+                expired = httpdate::parse_http_date(value).is_ok_and(|t| t <= SystemTime::now())
+            }
+            _ => {}
+        }
+    }
+    if cookie.name.is_empty() {
+        return None;
+    }
+    Some((cookie, expired))
+}
+
+pub(crate) fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+// Path=/a 匹配 /a 和 /a/b，但不匹配 /abc
+fn path_matches(request: &str, path: &str) -> bool {
+    match request.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+impl SessionCookie {
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        let domain = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(host, &self.domain)
+        };
+        domain && path_matches(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+}
+
+pub(crate) struct SessionStore {
+    path: PathBuf,
+    cookies: Mutex<Vec<SessionCookie>>,
+    // 命令行 --cookie 指定的值，只随本次请求发送，不写入会话文件
+    extra: Vec<(String, String)>,
+}
+
+impl SessionStore {
+    // 会话文件不存在或已损坏时从空会话开始
+    pub(crate) fn load(path: PathBuf, extra: Vec<(String, String)>) -> Self {
+        let cookies = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            cookies: Mutex::new(cookies),
+            extra,
+        }
+    }
+    fn save(&self, cookies: &[SessionCookie]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(cookies)?)?;
+        Ok(())
+    }
+}
+
+// 每次收到 Set-Cookie 都立即写回会话文件，下次使用同名会话时自动携带
+impl CookieStore for SessionStore {
+    fn set_cookies(&self, headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut cookies = self.cookies.lock().unwrap();
+        for (cookie, expired) in headers
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| parse_set_cookie(v, url))
+        {
+            cookies.retain(|c| {
+                !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path)
+            });
+            if !expired {
+                cookies.push(cookie);
+            }
+        }
+        if let Err(e) = self.save(&cookies) {
+            eprintln!("Failed to save session {}: {}", self.path.display(), e);
+        }
+    }
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let cookies = self.cookies.lock().unwrap();
+        let value = cookies
+            .iter()
+            .filter(|c| c.matches(url))
+            .filter(|c| !self.extra.iter().any(|(name, _)| *name == c.name))
+            .map(|c| format!("{}={}", c.name, c.value))
+            .chain(
+                self.extra
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value)),
+            )
+            .collect::<Vec<_>>()
+            .join("; ");
+        if value.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&value).ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{config_dir, Opts};
+    use crate::common::temp_path;
+    use crate::request::build_headers;
+    use clap::Parser;
+    use reqwest::header;
+
+    #[test]
+    fn parse_set_cookie_works() {
+        let url = Url::parse("https://api.example.com/login").unwrap();
+        let (cookie, expired) =
+            parse_set_cookie("sid=a=b; Path=/api; Domain=.Example.com; HttpOnly", &url).unwrap();
+        assert_eq!(
+            cookie,
+            SessionCookie {
+                name: "sid".into(),
+                value: "a=b".into(),
+                domain: "example.com".into(),
+                path: "/api".into(),
+                host_only: false,
+                secure: false,
+            }
+        );
+        assert!(!expired);
+        let (cookie, _) = parse_set_cookie("theme=dark; Secure", &url).unwrap();
+        assert_eq!(cookie.domain, "api.example.com");
+        assert_eq!(cookie.path, "/");
+        assert!(cookie.host_only);
+        assert!(cookie.secure);
+        // Domain 必须覆盖请求的主机
+        assert!(parse_set_cookie("sid=x; Domain=other.com", &url).is_none());
+        assert!(parse_set_cookie("sid=x; Domain=www.example.com", &url).is_none());
+        assert!(parse_set_cookie("sid=x; Domain=com", &url).is_none());
+        assert!(parse_set_cookie("sid=; Max-Age=0", &url).unwrap().1);
+        assert!(
+            parse_set_cookie("sid=x; Expires=Thu, 01 Jan 1970 00:00:00 GMT", &url)
+                .unwrap()
+                .1
+        );
+        assert!(parse_set_cookie("garbage", &url).is_none());
+    }
+    #[test]
+    fn session_store_persists_cookies() {
+        let path = temp_path("session-test.json");
+        let _ = fs::remove_file(&path);
+        let url = Url::parse("https://example.com/").unwrap();
+        let store = SessionStore::load(path.clone(), vec![]);
+        assert!(store.cookies(&url).is_none());
+        let set = [
+            HeaderValue::from_static("a=1"),
+            HeaderValue::from_static("b=2; Path=/admin"),
+            HeaderValue::from_static("c=3; Domain=example.com"),
+            HeaderValue::from_static("d=4; Secure"),
+            HeaderValue::from_static("e=5; Domain=evil.com"),
+        ];
+        store.set_cookies(&mut set.iter(), &url);
+
+        let store = SessionStore::load(path.clone(), vec![]);
+        let cookies = |url: &str| store.cookies(&Url::parse(url).unwrap());
+        assert_eq!(cookies("https://example.com/").unwrap(), "a=1; c=3; d=4");
+        assert_eq!(
+            cookies("https://example.com/admin/users").unwrap(),
+            "a=1; b=2; c=3; d=4"
+        );
+        assert_eq!(
+            cookies("https://example.com/administrator").unwrap(),
+            "a=1; c=3; d=4"
+        );
+        // 没有 Domain 的 cookie 不发给子域名，Secure 的 cookie 不走明文 http
+        assert_eq!(cookies("https://www.example.com/admin").unwrap(), "c=3");
+        assert_eq!(cookies("http://example.com/").unwrap(), "a=1; c=3");
+        assert!(cookies("https://evil.com/").is_none());
+        assert!(cookies("https://other.com/").is_none());
+        let delete = [
+            HeaderValue::from_static("a=; Max-Age=0"),
+            HeaderValue::from_static("c=; Max-Age=0; Domain=example.com"),
+            HeaderValue::from_static("d=; Max-Age=0"),
+        ];
+        store.set_cookies(&mut delete.iter(), &url);
+        assert!(SessionStore::load(path.clone(), vec![])
+            .cookies(&url)
+            .is_none());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(SessionStore::load(path.clone(), vec![])
+            .cookies(&url)
+            .is_none());
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn session_path_works() {
+        assert_eq!(
+            session_path("./my-session.json").unwrap(),
+            PathBuf::from("./my-session.json")
+        );
+        if let Some(dir) = config_dir() {
+            assert_eq!(
+                session_path("dev").unwrap(),
+                dir.join("sessions").join("dev.json")
+            );
+        }
+    }
+    #[test]
+    fn cookie_option_merges_with_session() {
+        let path = temp_path("session-cookie-test.json");
+        let url = Url::parse("https://example.com/").unwrap();
+        fs::write(
+            &path,
+            r#"[{"name":"a","value":"old","domain":"example.com","path":"/"},{"name":"s","value":"1","domain":"example.com","path":"/"}]"#,
+        )
+        .unwrap();
+        let store = SessionStore::load(path.clone(), vec![("a".into(), "new".into())]);
+        assert_eq!(store.cookies(&url).unwrap(), "s=1; a=new");
+        let opts = Opts::try_parse_from([
+            "httpie",
+            "--session",
+            path.to_str().unwrap(),
+            "--cookie",
+            "a=1",
+            "get",
+            "https://a.b",
+        ])
+        .unwrap();
+        assert!(build_headers(&opts).unwrap().get(header::COOKIE).is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+//! `--verbose-tls` 输出的连接和证书信息
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use std::fmt::Debug;
+
+#[derive(Debug)]
+pub(crate) struct TlsDetails {
+    subject: String,
+    issuer: String,
+    expires: String,
+}
+
+// reqwest 只提供服务器证书，TLS 版本和密码套件无法获取
+pub(crate) fn tls_details(der: &[u8]) -> Result<TlsDetails> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der)
+        .map_err(|e| anyhow!("Failed to parse server certificate: {}", e))?;
+    Ok(TlsDetails {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        expires: cert.validity().not_after.to_rfc2822(),
+    })
+}
+
+// 获取失败时只提示，不影响请求本身
+pub(crate) fn print_tls(details: Result<TlsDetails>) {
+    println!("{}", "[tls]".bold().truecolor(164, 111, 164));
+    match details {
+        Ok(details) => {
+            for (name, value) in [
+                ("version:", "n/a".to_string()),
+                ("cipher:", "n/a".to_string()),
+                ("subject:", details.subject),
+                ("issuer:", details.issuer),
+                ("expires:", details.expires),
+            ] {
+                println!("{} {}", name.truecolor(157, 173, 212), value);
+            }
+        }
+        Err(e) => println!("{}", format!("{:#}", e).yellow()),
+    }
+}
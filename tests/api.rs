@@ -0,0 +1,49 @@
+use httpie_rust::{execute_request, parse_kv_pair, parse_url, RequestItem, RequestSpec};
+use reqwest::{Client, Method};
+
+mod common;
+use common::{serve_once, temp_path};
+
+#[tokio::test]
+async fn execute_request_sends_spec() {
+    let (url, server) = serve_once("HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok");
+    let mut spec = RequestSpec::new(Method::POST, parse_url(&url).unwrap());
+    spec.query.push(parse_kv_pair("page=2").unwrap());
+    spec.items
+        .push("X-Api-Key:secret".parse::<RequestItem>().unwrap());
+    spec.items
+        .push("name=httpie".parse::<RequestItem>().unwrap());
+    let res = execute_request(&Client::new(), &spec).await.unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.text().await.unwrap(), "ok");
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /?page=2 HTTP/1.1"));
+    assert!(request.contains("x-api-key: secret"));
+    assert!(request.ends_with("{\"name\":\"httpie\"}"));
+}
+
+#[tokio::test]
+async fn execute_request_uploads_files_with_auth() {
+    let path = temp_path("api-upload.txt");
+    std::fs::write(&path, "notes").unwrap();
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    let mut spec = RequestSpec::new(Method::POST, parse_url(&url).unwrap());
+    spec.form = true;
+    spec.basic_auth = Some(("user".into(), "pass".into()));
+    spec.items
+        .push("title=weekly".parse::<RequestItem>().unwrap());
+    spec.items.push(
+        format!("doc@{}", path.display())
+            .parse::<RequestItem>()
+            .unwrap(),
+    );
+    let res = execute_request(&Client::new(), &spec).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let request = server.join().unwrap();
+    assert!(request.contains("authorization: Basic dXNlcjpwYXNz"));
+    assert!(request.contains("content-type: multipart/form-data; boundary="));
+    let name = path.file_name().unwrap().to_str().unwrap();
+    assert!(request.contains(&format!("name=\"doc\"; filename=\"{}\"", name)));
+    assert!(request.contains("name=\"title\"\r\n\r\nweekly"));
+    std::fs::remove_file(path).unwrap();
+}
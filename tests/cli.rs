@@ -0,0 +1,910 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+use std::thread;
+
+mod common;
+use common::{serve_once, temp_path};
+
+#[test]
+fn post_reads_body_from_stdin() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["post", &url, "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"{\"piped\":true}")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST / HTTP/1.1"));
+    assert!(request.contains("content-type: application/json"));
+    assert!(request.ends_with("\r\n\r\n{\"piped\":true}"));
+}
+
+#[test]
+fn ignore_stdin_leaves_piped_input_unread() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--ignore-stdin", "post", &url, "a=1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // 保持 stdin 打开且不写入，读取 stdin 的话会一直挂起
+    let stdin = child.stdin.take().unwrap();
+    let output = child.wait_with_output().unwrap();
+    drop(stdin);
+    assert!(output.status.success());
+    assert!(server.join().unwrap().ends_with("\r\n\r\n{\"a\":\"1\"}"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--ignore-stdin", "post", "http://127.0.0.1:9", "--stdin"])
+        .stdin(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--ignore-stdin"));
+}
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const JSON_RESPONSE: &str =
+    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 8\r\n\r\n{\"a\": 1}";
+
+#[test]
+fn prints_all_sections_by_default() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["get", &url]);
+    assert!(stdout.contains("[status]"));
+    assert!(stdout.contains("[headers]"));
+    assert!(stdout.contains("[body]"));
+}
+
+#[test]
+fn headers_flag_skips_body() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--headers", "get", &url]);
+    assert!(stdout.contains("[status]"));
+    assert!(stdout.contains("content-type: \"application/json\""));
+    assert!(!stdout.contains("[body]"));
+    assert!(!stdout.contains("\"a\""));
+}
+
+#[test]
+fn body_flag_prints_only_body() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--body", "get", &url]);
+    assert_eq!(stdout, "{\n  \"a\": 1\n}\n");
+}
+
+#[test]
+fn body_flag_summarizes_binary_responses() {
+    let (url, _server) =
+        serve_once("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\nPNG!");
+    let stdout = run(&["--body", "get", &url]);
+    assert_eq!(stdout, "<binary data: 4 B, image/png>\n");
+}
+
+#[test]
+fn check_status_sets_exit_code() {
+    let not_found = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+    let (url, _server) = serve_once(not_found);
+    let status = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--check-status", "get", &url])
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(4));
+    let (url, _server) = serve_once(not_found);
+    assert_eq!(
+        run(&["get", &url]).lines().nth(1),
+        Some("HTTP/1.1 404 Not Found")
+    );
+}
+
+#[test]
+fn expands_port_shorthand_to_localhost() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    let port = url.rsplit(':').next().unwrap();
+    run(&["get", &format!(":{}/ping", port)]);
+    assert!(server.join().unwrap().starts_with("GET /ping HTTP/1.1"));
+}
+
+#[test]
+fn offline_prints_request_without_sending() {
+    // 端口 1 上没有服务，真正发出请求就会失败
+    let stdout = run(&["--offline", "post", "http://127.0.0.1:1/items", "name=demo"]);
+    assert!(stdout.contains("[request]"));
+    assert!(stdout.contains("POST http://127.0.0.1:1/items"));
+    assert!(stdout.contains("{\"name\":\"demo\"}"));
+    assert!(!stdout.contains("[status]"));
+    run(&["--dry-run", "get", "http://127.0.0.1:1/"]);
+}
+
+#[test]
+fn curl_with_offline_prints_only_the_command() {
+    let stdout = run(&["--offline", "--curl", "put", "http://127.0.0.1:1/a", "x=1"]);
+    assert!(stdout.starts_with("curl -X PUT "));
+    assert!(stdout.contains("-H 'content-type: application/json'"));
+    assert!(stdout.ends_with("--data-binary '{\"x\":\"1\"}' http://127.0.0.1:1/a\n"));
+    assert!(!stdout.contains("[request]"));
+
+    // --compress 时 curl 命令使用压缩前的 body
+    let stdout = run(&[
+        "--offline",
+        "--curl",
+        "--compress",
+        "post",
+        "http://127.0.0.1:1/a",
+        "x=1",
+    ]);
+    assert!(stdout.ends_with("--data-binary '{\"x\":\"1\"}' http://127.0.0.1:1/a\n"));
+    assert!(!stdout.contains("content-encoding"));
+}
+
+#[test]
+fn get_fetches_multiple_urls_in_sequence() {
+    let (first, _server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none");
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["get", &first, "http://127.0.0.1:1/"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("──── {} ────", first)));
+    assert!(stdout.contains("──── http://127.0.0.1:1/ ────"));
+    assert!(stdout.ends_with("1 of 2 requests succeeded\n"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error:"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args([
+            "get",
+            "--fail-fast",
+            "http://127.0.0.1:1/",
+            "http://127.0.0.1:1/b",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("/b ────"));
+    assert!(!stdout.contains("requests succeeded"));
+}
+
+#[test]
+fn parallel_fetches_urls_concurrently() {
+    // 服务器先接受两个连接再统一响应，顺序请求时第一个请求会一直等待
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let mut streams = Vec::new();
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            let path = String::from_utf8_lossy(&buf[..n])
+                .split(' ')
+                .nth(1)
+                .unwrap()
+                .to_string();
+            streams.push((stream, path));
+        }
+        for (mut stream, path) in streams {
+            let response = format!("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}", &path[..2]);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    let (a, b) = (format!("{}/a", url), format!("{}/b", url));
+    let stdout = run(&["--timeout", "10", "get", "--parallel", "2", &a, &b]);
+    server.join().unwrap();
+    let first = stdout.find(&format!("──── {} ────", a)).unwrap();
+    let second = stdout.find(&format!("──── {} ────", b)).unwrap();
+    assert!(first < second);
+    assert!(stdout[first..second].contains("[body]\n/a"));
+    assert!(stdout[second..].contains("[body]\n/b"));
+    assert!(stdout.ends_with("2 of 2 requests succeeded\n"));
+}
+
+#[test]
+fn select_extracts_json_value() {
+    let (url, _server) = serve_once(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 29\r\n\r\n{\"data\":{\"items\":[{\"id\":7}]}}",
+    );
+    assert_eq!(
+        run(&["--body", "--select", "data.items.0", "get", &url]),
+        "{\n  \"id\": 7\n}\n"
+    );
+    let (url, _server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--select", "a", "get", &url])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--select requires a JSON response"));
+}
+
+#[test]
+fn raw_output_writes_body_bytes_without_escapes() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--color", "always", "--body", "--raw-output", "get", &url]);
+    assert_eq!(stdout, "{\"a\": 1}");
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--color", "always", "--raw-output", "get", &url]);
+    assert!(stdout.ends_with("\n{\"a\": 1}"));
+    assert!(!stdout.contains("[body]"));
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--headers", "--raw-output", "get", &url]);
+    assert!(!stdout.contains("{\"a\": 1}"));
+}
+
+#[test]
+fn repeat_prints_each_attempt_and_summary() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming().take(3) {
+            let mut stream = stream.unwrap();
+            let mut buf = [0; 4096];
+            let _ = stream.read(&mut buf);
+            let response = "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 4\r\n\r\nbody";
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    let stdout = run(&["--repeat", "3", "get", &url]);
+    assert!(stdout.starts_with("[1/3] 200 OK "));
+    assert!(stdout.contains("\n[2/3] 200 OK "));
+    assert!(stdout.contains("\n[3/3] 200 OK "));
+    assert_eq!(stdout.matches("[body]").count(), 1);
+    assert!(stdout.contains("[summary] 3 requests, min "));
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_aborts_and_removes_partial_output() {
+    use std::time::Duration;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 4096];
+        let _ = stream.read(&mut buf);
+        // 只发送一部分 body，然后一直挂起
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial");
+        thread::sleep(Duration::from_secs(10));
+    });
+    let path = temp_path("interrupted.bin");
+    let child = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["-o", path.to_str().unwrap(), "get", &url])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    for _ in 0..50 {
+        if path.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    thread::sleep(Duration::from_millis(200));
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Interrupted"));
+    assert!(!path.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_does_not_wait_for_the_pager() {
+    use std::time::{Duration, Instant};
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    // body 交给分页程序后，分页程序一直不退出
+    let mut child = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--pager", "get", &url])
+        .env("PAGER", "sleep 10")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(500));
+    let start = Instant::now();
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn not_modified_prints_concise_message() {
+    let (url, server) =
+        serve_once("HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nContent-Length: 0\r\n\r\n");
+    let stdout = run(&["--etag", "v1", "get", &url]);
+    assert!(server.join().unwrap().contains("if-none-match: \"v1\""));
+    assert!(stdout.contains("HTTP/1.1 304 Not Modified"));
+    assert!(stdout.ends_with("Not Modified, the cached copy is still valid\n"));
+    assert!(!stdout.contains("[body]"));
+}
+
+#[test]
+fn range_writes_partial_content_to_output() {
+    let (url, server) = serve_once(
+        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-3/10\r\nContent-Length: 4\r\n\r\nabcd",
+    );
+    let path = temp_path("range.bin");
+    let stdout = run(&["--range", "0-3", "-o", path.to_str().unwrap(), "get", &url]);
+    assert!(server.join().unwrap().contains("range: bytes=0-3"));
+    assert!(stdout.contains("HTTP/1.1 206 Partial Content bytes 0-3/10"));
+    assert_eq!(std::fs::read(&path).unwrap(), b"abcd");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn auth_without_password_fails_when_stdin_is_not_a_terminal() {
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--auth", "user", "get", "http://127.0.0.1:9"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No password given for --auth user"));
+}
+
+#[test]
+fn resolve_pins_host_to_address() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    let port = url.rsplit(':').next().unwrap();
+    let pinned = format!("httpie-rust.invalid:{}:127.0.0.1", port);
+    let target = format!("http://httpie-rust.invalid:{}/", port);
+    let stdout = run(&["--resolve", &pinned, "get", &target]);
+    assert!(server
+        .join()
+        .unwrap()
+        .contains(&format!("host: httpie-rust.invalid:{}", port)));
+    assert!(stdout.contains("200 OK"));
+}
+
+#[test]
+fn porcelain_prints_tab_separated_summary_and_body() {
+    let (url, server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--porcelain", "--color", "always", "get", &url]);
+    server.join().unwrap();
+    assert_eq!(stdout, "200\tapplication/json\t8\n{\"a\": 1}");
+
+    let (url, server) = serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    let stdout = run(&["--porcelain", "get", &url]);
+    server.join().unwrap();
+    assert_eq!(stdout, "404\t-\t0\n");
+}
+
+#[test]
+fn timings_prints_phase_breakdown() {
+    // 只有实际请求这一个连接
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    // 用主机名才会经过 DNS 解析
+    let url = url.replace("127.0.0.1", "localhost");
+    let stdout = run(&["--timings", "get", &url]);
+    server.join().unwrap();
+    let timing = &stdout[stdout.find("[timing]").unwrap()..];
+    for phase in ["connect: n/a", "tls:     n/a"] {
+        assert!(timing.contains(phase), "{}", phase);
+    }
+    assert!(timing.contains("dns:     "));
+    assert!(!timing.contains("dns:     n/a"));
+    assert!(timing.contains("ttfb:    "));
+    assert!(!timing.contains("ttfb:    n/a"));
+    assert!(timing.contains("total:   "));
+}
+
+#[test]
+fn interface_binds_the_local_address() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    let stdout = run(&["--interface", "127.0.0.1", "get", &url]);
+    server.join().unwrap();
+    assert!(stdout.contains("200 OK"));
+    // 192.0.2.1 是文档保留地址，不属于本机，即使服务器在监听，绑定也必然失败
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--interface", "192.0.2.1", "get", &url])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+// 支持 keep-alive 的本地服务器，返回地址和已接受的连接数
+fn serve_keepalive() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut stream = stream.unwrap();
+            thread::spawn(move || {
+                let mut buf = [0; 4096];
+                while matches!(stream.read(&mut buf), Ok(n) if n > 0) {
+                    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                    if stream.write_all(response).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (url, connections)
+}
+
+#[test]
+fn no_keepalive_opens_a_connection_per_request() {
+    use std::sync::atomic::Ordering;
+    let (url, connections) = serve_keepalive();
+    run(&["--repeat", "3", "get", &url]);
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    let (url, connections) = serve_keepalive();
+    run(&["--no-keepalive", "--repeat", "3", "get", &url]);
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn warns_when_body_is_shorter_than_content_length() {
+    let (url, server) =
+        serve_once("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 10\r\n\r\nabc");
+    let stdout = run(&["get", &url]);
+    server.join().unwrap();
+    assert!(stdout.contains("abc\n"));
+    assert!(stdout.contains("warning: body truncated, received 3 of 10 bytes"));
+}
+
+#[test]
+fn max_time_bounds_slow_requests() {
+    use std::time::{Duration, Instant};
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 4096];
+        let _ = stream.read(&mut buf);
+        // 响应头之后迟迟不发送 body
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nab");
+        thread::sleep(Duration::from_secs(10));
+    });
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--max-time", "0.5", "get", &url])
+        .output()
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(
+        "Gave up after --max-time 0.5 s: 1 attempt sent, response headers received, body incomplete"
+    ));
+}
+
+#[test]
+fn max_time_bounds_each_repeat() {
+    use std::time::{Duration, Instant};
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let mut streams = Vec::new();
+        for stream in listener.incoming().take(2) {
+            let mut stream = stream.unwrap();
+            let mut buf = [0; 4096];
+            let _ = stream.read(&mut buf);
+            // 第一次正常响应，第二次不响应
+            if streams.is_empty() {
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+            }
+            streams.push(stream);
+        }
+        thread::sleep(Duration::from_secs(10));
+    });
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--max-time", "0.5", "--repeat", "3", "get", &url])
+        .output()
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("[1/3] 200 OK "));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Gave up after --max-time 0.5 s: 1 attempt sent, no response received"));
+}
+
+#[test]
+fn saved_request_can_be_replayed() {
+    let path = temp_path("cli-saved-request.json");
+    let (url, _server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    let (other, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    run(&[
+        "--offline",
+        "--save-request",
+        path.to_str().unwrap(),
+        "post",
+        &format!("{}/items", url),
+        "X-Trace:1",
+        "name=demo",
+    ]);
+    run(&[
+        "replay",
+        path.to_str().unwrap(),
+        "--url",
+        &format!("{}/copy", other),
+    ]);
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /copy HTTP/1.1"));
+    assert!(request.contains("x-trace: 1"));
+    assert!(request.ends_with("\r\n\r\n{\"name\":\"demo\"}"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn stream_prints_text_as_it_arrives() {
+    use std::io::{BufRead, BufReader};
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sent, wait) = std::sync::mpsc::channel::<()>();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 4096];
+        let n = stream.read(&mut buf).unwrap();
+        let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nfirst\n\r\n")
+            .unwrap();
+        // 第一块输出之前不发送剩余内容；"é" 跨两个块发送
+        wait.recv().unwrap();
+        stream
+            .write_all(b"1\r\n\xc3\r\n2\r\n\xa9\n\r\n0\r\n\r\n")
+            .unwrap();
+        request
+    });
+    let mut child = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--body", "--stream", "get", &url])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "first\n");
+    sent.send(()).unwrap();
+    let mut rest = String::new();
+    stdout.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "é\n");
+    assert!(child.wait().unwrap().success());
+    assert!(server.join().unwrap().contains("accept-encoding: identity"));
+}
+
+#[test]
+fn prints_server_sent_events() {
+    let (url, _server) = serve_once(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n: hello\n\nevent: tick\nid: 1\ndata: one\n\ndata: two\ndata: lines\n\n",
+    );
+    let stdout = run(&["--body", "get", &url]);
+    assert_eq!(
+        stdout,
+        "event: tick (id 1)\none\nevent: message\ntwo\nlines\n"
+    );
+}
+
+#[test]
+fn fail_suppresses_output_on_error_status() {
+    let (url, _server) =
+        serve_once("HTTP/1.1 503 Service Unavailable\r\nContent-Length: 11\r\n\r\nmaintenance");
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--fail", "get", &url])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("HTTP 503 Service Unavailable"));
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    assert!(run(&["--fail", "get", &url]).contains("[body]"));
+}
+
+#[test]
+fn body_only_on_error_prints_only_failed_bodies() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    assert_eq!(run(&["--body-only-on-error", "get", &url]), "");
+    let (url, _server) = serve_once(
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 12\r\n\r\ndb is down\r\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--body-only-on-error", "get", &url])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("db is down"));
+    assert!(!stdout.contains("500 Internal Server Error"));
+}
+
+#[test]
+fn host_header_overrides_url_host() {
+    let (url, server) = serve_once(JSON_RESPONSE);
+    run(&["--host-header", "vhost.example:8080", "get", &url]);
+    let request = server.join().unwrap().to_lowercase();
+    assert!(request.contains("host: vhost.example:8080\r\n"));
+    assert!(!request.contains("host: 127.0.0.1"));
+}
+
+#[test]
+fn host_header_is_dropped_on_cross_host_redirects() {
+    let (target, target_server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    // 127.0.0.1 和 localhost 是不同的主机
+    let target = target.replace("127.0.0.1", "localhost");
+    let redirect: &'static str = Box::leak(
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}/next\r\nContent-Length: 0\r\n\r\n",
+            target
+        )
+        .into_boxed_str(),
+    );
+    let (url, server) = serve_once(redirect);
+    let stdout = run(&["--host-header", "vhost.example", "get", &url]);
+    assert!(stdout.contains("ok"));
+    assert!(server
+        .join()
+        .unwrap()
+        .to_lowercase()
+        .contains("host: vhost.example\r\n"));
+    let followed = target_server.join().unwrap().to_lowercase();
+    assert!(followed.starts_with("get /next "));
+    assert!(followed.contains(&format!(
+        "host: {}\r\n",
+        target.trim_start_matches("http://")
+    )));
+    assert!(!followed.contains("vhost.example"));
+}
+
+fn run_failing(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn host_header_redirects_respect_the_limit() {
+    let (url, _server) =
+        serve_once("HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n");
+    let stderr = run_failing(&[
+        "--host-header",
+        "vhost.example",
+        "--max-redirects",
+        "0",
+        "get",
+        &url,
+    ]);
+    assert!(stderr.contains("Exceeded --max-redirects 0"));
+
+    // 304 不是重定向，即使带有 Location 也不跟随
+    let (url, _server) =
+        serve_once("HTTP/1.1 304 Not Modified\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n");
+    let stdout = run(&["--host-header", "vhost.example", "get", &url]);
+    assert!(stdout.contains("304 Not Modified"));
+}
+
+#[test]
+fn no_pager_prints_directly() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--no-pager", "get", &url])
+        .env("PAGER", "false")
+        .output()
+        .unwrap()
+        .stdout;
+    assert!(String::from_utf8_lossy(&stdout).contains("[body]"));
+}
+
+#[test]
+fn no_body_highlight_keeps_header_colors() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let stdout = run(&["--color", "always", "--no-body-highlight", "get", &url]);
+    let (head, body) = stdout.split_once("{").unwrap();
+    assert!(head.contains("\x1b["));
+    assert_eq!(body, "\n  \"a\": 1\n}\n");
+}
+
+#[test]
+fn connection_failures_are_explained() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let url = format!("http://127.0.0.1:{}", port);
+    let stderr = run_failing(&["get", &url]);
+    assert_eq!(
+        stderr.trim(),
+        format!("Error: Connection refused to 127.0.0.1:{}", port)
+    );
+    let stderr = run_failing(&["--debug", "get", &url]);
+    assert!(stderr.starts_with(&format!("Error: Connection refused to 127.0.0.1:{}", port)));
+    assert!(stderr.contains("Caused by"));
+
+    let stderr = run_failing(&["get", "http://no-such-host.invalid/"]);
+    assert_eq!(
+        stderr.trim(),
+        "Error: Could not resolve host no-such-host.invalid"
+    );
+
+    let url = format!("https://localhost:{}", serve_tls(1));
+    let stderr = run_failing(&["get", &url]);
+    assert!(stderr.starts_with("Error: TLS certificate verification failed for localhost"));
+    assert!(stderr.contains("--insecure"));
+}
+
+#[test]
+fn requests_ipv6_literal_urls() {
+    // 没有 IPv6 回环地址的环境直接跳过
+    let listener = match TcpListener::bind("[::1]:0") {
+        Ok(listener) => listener,
+        Err(_) => return,
+    };
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 4096];
+        let n = stream.read(&mut buf).unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    });
+    run(&["get", &format!("[::1]:{}/v6", port)]);
+    let request = server.join().unwrap();
+    assert!(request.starts_with("GET /v6 HTTP/1.1"));
+    assert!(request.contains(&format!("host: [::1]:{}", port)));
+}
+
+#[test]
+fn log_level_writes_only_to_stderr() {
+    let (url, _server) = serve_once(JSON_RESPONSE);
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["--body", "--log-level", "debug", "get", &url])
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\n  \"a\": 1\n}\n"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("sending request"));
+    assert!(stderr.contains("response headers received"));
+}
+
+// 本地 TLS 服务器，使用 rcgen 生成的自签名证书，处理 connections 个连接后退出
+fn serve_tls(connections: usize) -> u16 {
+    use std::sync::Arc;
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![rustls::Certificate(cert.serialize_der().unwrap())],
+            rustls::PrivateKey(cert.serialize_private_key_der()),
+        )
+        .unwrap();
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().take(connections) {
+            let session = rustls::ServerConnection::new(config.clone()).unwrap();
+            let mut tls = rustls::StreamOwned::new(session, stream.unwrap());
+            let mut buf = [0; 4096];
+            if matches!(tls.read(&mut buf), Ok(n) if n > 0) {
+                let _ = tls.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+                let _ = tls.flush();
+            }
+        }
+    });
+    port
+}
+
+#[test]
+fn verbose_tls_prints_handshake_details() {
+    // 证书来自实际请求的连接，只有一个连接
+    let url = format!("https://localhost:{}", serve_tls(1));
+    let stdout = run(&["--verbose-tls", "--insecure", "get", &url]);
+    let tls = stdout.find("[tls]").unwrap();
+    assert!(tls < stdout.find("[status]").unwrap());
+    assert!(stdout.contains("version: n/a"));
+    assert!(stdout.contains("subject: CN=rcgen self signed cert"));
+
+    let port = serve_tls(1);
+    let url = format!("https://probe.test:{}", port);
+    let resolve = format!("probe.test:{}:127.0.0.1", port);
+    let stdout = run(&[
+        "--verbose-tls",
+        "--insecure",
+        "--resolve",
+        &resolve,
+        "get",
+        &url,
+    ]);
+    assert!(stdout.contains("subject: CN=rcgen self signed cert"));
+
+    // 普通 HTTP 没有 [tls]
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    let stdout = run(&["--verbose-tls", "get", &url]);
+    server.join().unwrap();
+    assert!(!stdout.contains("[tls"));
+}
+
+#[test]
+fn format_highlights_local_json_file() {
+    let path = temp_path("format.json");
+    std::fs::write(&path, "{\"a\":[1,2]}").unwrap();
+    let stdout = run(&["format", path.to_str().unwrap()]);
+    assert_eq!(stdout, "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n");
+    let colored = run(&["--color", "always", "format", path.to_str().unwrap()]);
+    assert!(colored.contains("\x1b[38;2;"));
+    std::fs::remove_file(&path).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+        .args(["format", "/nonexistent/httpie-rust.json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Failed to read /nonexistent/httpie-rust.json"));
+}
+
+#[test]
+fn history_records_requests_and_lists_them() {
+    let config = temp_path("history-cli");
+    let _ = std::fs::remove_dir_all(&config);
+    let history = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_httpie-rust"))
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    history(&["get", &url]);
+    server.join().unwrap();
+    assert!(!config.join("httpie-rust/history.jsonl").exists());
+
+    let (url, server) = serve_once("HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n");
+    history(&["--history", "post", &url, "a=1"]);
+    server.join().unwrap();
+    let listed = history(&["history"]);
+    assert!(listed.ends_with(&format!("  POST {}/ 201\n", url)));
+    assert_eq!(listed.lines().count(), 1);
+    std::fs::remove_dir_all(&config).unwrap();
+}
@@ -0,0 +1,80 @@
+// 各测试 crate 只用到其中一部分
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+// 读到完整的请求头和 Content-Length 指定长度的 body 为止，客户端断开时返回已读到的部分
+fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let n = stream.read(&mut buf).unwrap_or(0);
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end]
+                .lines()
+                .find_map(|l| {
+                    let (name, value) = l.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if request.len() >= end + 4 + length {
+                break;
+            }
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&request).into_owned()
+}
+
+// 启动一个只处理一次请求的本地服务器，返回地址和收到的原始请求
+pub fn serve_once(
+    response: impl AsRef<[u8]> + Send + 'static,
+) -> (String, thread::JoinHandle<String>) {
+    serve_after(Duration::ZERO, response)
+}
+
+// 与 serve_once 相同，但读完请求后等待 delay 再响应，用于测试超时
+pub fn serve_after(
+    delay: Duration,
+    response: impl AsRef<[u8]> + Send + 'static,
+) -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let request = read_request(&mut stream);
+        thread::sleep(delay);
+        // 客户端超时后已经断开，写入失败也无妨
+        let _ = stream.write_all(response.as_ref());
+        request
+    });
+    (addr, handle)
+}
+
+// 依次用 responses 回应每个连接上的一个请求，用于重定向和重试
+pub fn serve_sequence(responses: Vec<&'static str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_request(&mut stream);
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    addr
+}
+
+// 临时文件名带上进程号，同时运行的多个测试进程不会互相覆盖
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("httpie-rust-{}-{}", std::process::id(), name))
+}